@@ -0,0 +1,109 @@
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+const INSTRUCTIONS_PATH: &str = "src/parser/magic_bits/instructions.in";
+
+/// One row of `instructions.in`: an `Opcode` variant name, the payload struct it wraps, and
+/// whether the disassembler should treat it as a branch or a terminator.
+struct Instruction {
+    name: String,
+    payload: String,
+    is_branch: bool,
+    is_terminator: bool,
+}
+
+fn parse_instructions(src: &str) -> Vec<Instruction> {
+    src.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(
+                fields.len(),
+                4,
+                "malformed instructions.in line (expected `Name Payload Branch Terminator`): {line}"
+            );
+            Instruction {
+                name: fields[0].to_string(),
+                payload: fields[1].to_string(),
+                is_branch: fields[2] == "yes",
+                is_terminator: fields[3] == "yes",
+            }
+        })
+        .collect()
+}
+
+fn generate(instructions: &[Instruction]) -> String {
+    let mut out = String::new();
+    out.push_str("// @generated from src/parser/magic_bits/instructions.in by build.rs. Do not edit by hand.\n\n");
+
+    out.push_str("#[derive(Debug, Clone, PartialEq, Eq, ToString)]\npub enum Opcode {\n");
+    for ins in instructions {
+        let _ = writeln!(out, "    {}({}),", ins.name, payload_type(&ins.payload));
+    }
+    out.push_str("}\n\n");
+
+    out.push_str("impl Opcode {\n");
+    out.push_str("    /// Uniform bit accessor across every opcode shape, replacing the hand-written\n");
+    out.push_str("    /// `get_bits` match that used to live in `disassemble.rs`.\n");
+    out.push_str("    pub fn bits(&self) -> &[u16] {\n        match self {\n");
+    for ins in instructions {
+        let _ = writeln!(out, "            Opcode::{}(o) => &o.bits,", ins.name);
+    }
+    out.push_str("        }\n    }\n\n");
+
+    let _ = writeln!(
+        out,
+        "    pub fn is_branch(&self) -> bool {{\n        matches!(self, {})\n    }}\n",
+        match_pattern(instructions, |i| i.is_branch)
+    );
+
+    let _ = writeln!(
+        out,
+        "    pub fn is_terminator(&self) -> bool {{\n        matches!(self, {})\n    }}",
+        match_pattern(instructions, |i| i.is_terminator)
+    );
+    out.push_str("}\n");
+
+    out
+}
+
+fn match_pattern(instructions: &[Instruction], pred: impl Fn(&&Instruction) -> bool) -> String {
+    let arms: Vec<String> = instructions
+        .iter()
+        .filter(pred)
+        .map(|ins| format!("Opcode::{}(_)", ins.name))
+        .collect();
+    assert!(
+        !arms.is_empty(),
+        "instructions.in must flag at least one branch and one terminator opcode"
+    );
+    arms.join(" | ")
+}
+
+fn payload_type(payload: &str) -> &'static str {
+    match payload {
+        "Default" => "DefaultOpcode",
+        "Binary" => "BinaryOpcode",
+        "Unary" => "UnaryOpcode",
+        "NewLiteral" => "NewLiteralOpcode",
+        "Heap" => "ClosureOpcode",
+        "CondJump" => "CondJumpOpcode",
+        other => panic!("unknown payload type '{other}' in instructions.in"),
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={INSTRUCTIONS_PATH}");
+
+    let src = fs::read_to_string(INSTRUCTIONS_PATH)
+        .unwrap_or_else(|e| panic!("failed to read {INSTRUCTIONS_PATH}: {e}"));
+    let instructions = parse_instructions(&src);
+    let generated = generate(&instructions);
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set");
+    fs::write(Path::new(&out_dir).join("opcodes.rs"), generated)
+        .expect("failed to write generated opcodes.rs");
+}
@@ -1,6 +1,6 @@
 use crate::disassembler::RecursiveDisassembler;
 use crate::parser::{
-    functions::FindFunctions, magic_bits::{OpcodeParser, Opcode, normalize_bits}, offset::FindOffset,
+    functions::FindFunctions, magic_bits::{OpcodeParser, normalize_bits}, offset::FindOffset,
     payload::PayloadKeyExtractor, vm::ScriptVisitor,
 };
 use anyhow::Context;
@@ -34,28 +34,13 @@ pub fn parse_script_interpreter<'a>(
 
     let mut opcode_to_function_name = FxHashMap::default();
 
-    // Helper local pour extraire les bits sans erreur de lifetime
-    fn get_bits(op: &Opcode) -> &[u16] {
-        match op {
-            Opcode::ArrayPush(o) | Opcode::Throw(o) | Opcode::Bind(o) | Opcode::RegisterVMFunction(o) |
-            Opcode::NewObject(o) | Opcode::Pop(o) | Opcode::SetProperty(o) | Opcode::GetProperty(o) |
-            Opcode::SplicePop(o) | Opcode::CallFuncNoContext(o) | Opcode::SwapRegister(o) |
-            Opcode::NewArray(o) | Opcode::Jump(o) | Opcode::JumpIf(o) | Opcode::Move(o) | Opcode::Call(o) => &o.bits,
-            Opcode::Binary(o) => &o.bits,
-            Opcode::Unary(o) => &o.bits,
-            Opcode::NewLiteral(o) => &o.bits,
-            Opcode::Heap(o) => &o.bits,
-        }
-    }
-
     // After building opcode_parser.opcodes (map<u16, Opcode>)
     eprintln!("[disassemble] opcode table size = {}", opcode_parser.opcodes.len());
 
     // Build normalized opcode map: normalized_bits -> opcode_key
     let mut normalized_opcode_map: std::collections::HashMap<Vec<u16>, u16> = Default::default();
     for (k, v) in &opcode_parser.opcodes {
-        let bits = get_bits(v); // existing helper to extract Vec<u16> from Opcode enum
-        let normalized = normalize_bits(bits);
+        let normalized = normalize_bits(v.bits());
         if !normalized.is_empty() {
             normalized_opcode_map.insert(normalized, *k);
         }
@@ -106,7 +91,10 @@ pub fn parse_script_interpreter<'a>(
     let mut vm_bytecode_visitor = ScriptVisitor::default();
     vm_bytecode_visitor.visit_program(program);
     
-    let initial_vm = vm_bytecode_visitor.initial_vm.as_ref().context("could not find initial vm")?;
+    let initial_vm = &vm_bytecode_visitor
+        .initial_vm()
+        .context("could not find initial vm")?
+        .value;
 
     Ok((
         RecursiveDisassembler::new(
@@ -0,0 +1,302 @@
+use crate::parser::magic_bits::Opcode;
+use anyhow::Context;
+use oxc_ast::ast::Expression;
+use rustc_hash::FxHashMap;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+
+pub mod disassemble;
+
+/// Reconstructs the Turnstile VM's opcode table into something that can be stepped through or
+/// visualized, given the bit-masking key/offset `FindOffset`/`FindFunctions` recovered from the
+/// obfuscated script plus the entry opcode `ScriptVisitor` found.
+pub struct RecursiveDisassembler<'a> {
+    pub opcodes: FxHashMap<u16, Opcode>,
+    #[allow(dead_code)]
+    key_expr: Expression<'a>,
+    key: u16,
+    #[allow(dead_code)]
+    offset: u16,
+    pub initial_vm: u16,
+}
+
+impl<'a> RecursiveDisassembler<'a> {
+    pub fn new(
+        opcodes: FxHashMap<u16, Opcode>,
+        key_expr: Expression<'a>,
+        key: u16,
+        offset: u16,
+        initial_vm: &str,
+    ) -> Result<Self, anyhow::Error> {
+        let initial_vm = initial_vm
+            .parse::<u16>()
+            .with_context(|| format!("initial vm index '{initial_vm}' is not a valid opcode key"))?;
+
+        Ok(Self {
+            opcodes,
+            key_expr,
+            key,
+            offset,
+            initial_vm,
+        })
+    }
+
+    /// Best-effort jump-target resolution: the bits captured for `Jump`/`JumpIf` are the operand
+    /// words feeding the VM's register-masking step (`raw ^ key`, the same decode
+    /// `interpreter::OpcodeVM::resolve_jump` applies), so the `nth` bit XORed against the
+    /// recovered key is our candidate target register. Unresolved/out-of-range targets are
+    /// simply omitted from the graph.
+    fn resolve_target(&self, bits: &[u16], nth: usize) -> Option<u16> {
+        let raw = *bits.get(nth)?;
+        let target = raw ^ self.key;
+        self.opcodes.contains_key(&target).then_some(target)
+    }
+
+    /// Emits a Graphviz DOT digraph of the recovered opcode table's control flow, for
+    /// visualizing how the interpreter's reconstructed dispatch actually branches. Takes
+    /// `opcode_to_function_name` rather than storing it on `Self`, since it's a table
+    /// `parse_script_interpreter` builds alongside this disassembler from data the disassembler
+    /// itself doesn't otherwise need.
+    pub fn to_dot(&self, opcode_to_function_name: &FxHashMap<String, String>) -> String {
+        let mut keys: Vec<u16> = self.opcodes.keys().copied().collect();
+        keys.sort_unstable();
+
+        let mut dot = String::new();
+        dot.push_str("digraph {\n");
+        dot.push_str("    rankdir=LR;\n");
+
+        for &key in &keys {
+            let opcode = &self.opcodes[&key];
+            let variant = opcode.to_string();
+            let label = match opcode_to_function_name.get(&key.to_string()) {
+                Some(name) => format!("{key}: {variant} ({name})"),
+                None => format!("{key}: {variant}"),
+            };
+            let shape = if key == self.initial_vm {
+                "doublecircle"
+            } else {
+                "box"
+            };
+            let _ = writeln!(
+                dot,
+                "    n{key} [label=\"{}\", shape={shape}];",
+                escape_dot_label(&label)
+            );
+        }
+
+        for &key in &keys {
+            let opcode = &self.opcodes[&key];
+            match opcode {
+                Opcode::Jump(_) => {
+                    if let Some(target) = self.resolve_target(opcode.bits(), 0) {
+                        let _ = writeln!(dot, "    n{key} -> n{target};");
+                    }
+                }
+                Opcode::JumpIf(_) => {
+                    if let Some(target) = self.resolve_target(opcode.bits(), 0) {
+                        let _ =
+                            writeln!(dot, "    n{key} -> n{target} [label=\"true\", color=green];");
+                    }
+                    if let Some(target) = self.resolve_target(opcode.bits(), 1) {
+                        let _ =
+                            writeln!(dot, "    n{key} -> n{target} [label=\"false\", color=red];");
+                    }
+                }
+                Opcode::JumpIfTrue(_) => {
+                    if let Some(target) = self.resolve_target(opcode.bits(), 0) {
+                        let _ =
+                            writeln!(dot, "    n{key} -> n{target} [label=\"true\", color=green];");
+                    }
+                    if let Some(&next) = keys.iter().find(|&&k| k > key) {
+                        let _ = writeln!(dot, "    n{key} -> n{next} [label=\"false\", style=dashed];");
+                    }
+                }
+                Opcode::JumpIfFalse(_) => {
+                    if let Some(target) = self.resolve_target(opcode.bits(), 0) {
+                        let _ =
+                            writeln!(dot, "    n{key} -> n{target} [label=\"false\", color=red];");
+                    }
+                    if let Some(&next) = keys.iter().find(|&&k| k > key) {
+                        let _ = writeln!(dot, "    n{key} -> n{next} [label=\"true\", style=dashed];");
+                    }
+                }
+                _ if opcode.is_terminator() => {}
+                _ => {
+                    if let Some(&next) = keys.iter().find(|&&k| k > key) {
+                        let _ = writeln!(dot, "    n{key} -> n{next} [style=dashed];");
+                    }
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// CFG successors of `key`: both `resolve_target` branches for `Jump`/`JumpIf`, no
+    /// successors for a terminator, otherwise fall-through to the next opcode in key order -
+    /// the same edge rules `to_dot` draws, reused here for the liveness fixpoint below.
+    fn successors(&self, key: u16, keys: &[u16]) -> Vec<u16> {
+        let opcode = &self.opcodes[&key];
+        match opcode {
+            Opcode::Jump(_) => self.resolve_target(opcode.bits(), 0).into_iter().collect(),
+            Opcode::JumpIf(_) => [
+                self.resolve_target(opcode.bits(), 0),
+                self.resolve_target(opcode.bits(), 1),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            Opcode::JumpIfTrue(_) | Opcode::JumpIfFalse(_) => [
+                self.resolve_target(opcode.bits(), 0),
+                keys.iter().copied().find(|&k| k > key),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            _ if opcode.is_terminator() => Vec::new(),
+            _ => keys.iter().copied().find(|&k| k > key).into_iter().collect(),
+        }
+    }
+
+    /// Heuristic def/use for one opcode: `Jump`/`JumpIf`/terminators don't write a register, so
+    /// their bits are all treated as uses; everything else follows the convention the request
+    /// calls out (`Move`, `SetProperty`, `Binary`, `NewLiteral`, ...) of writing their first bit
+    /// as the destination slot and reading the rest. Like `resolve_target`, this is a stand-in
+    /// for semantics we haven't statically recovered - good enough to flag likely dead stores,
+    /// not a verified register allocation.
+    fn def_use(opcode: &Opcode) -> (Option<u16>, Vec<u16>) {
+        // `JumpIfTrue`/`JumpIfFalse` keep their condition register in `test` rather than folded
+        // into `bits()` (see `CondJumpOpcode`), so it has to be added back in by hand to still
+        // count as a use - otherwise the condition would look dead the moment these replaced the
+        // old undifferentiated `JumpIf`.
+        match opcode {
+            Opcode::JumpIfTrue(op) | Opcode::JumpIfFalse(op) => {
+                let mut uses = vec![op.test];
+                uses.extend(&op.bits);
+                return (None, uses);
+            }
+            _ => {}
+        }
+
+        if opcode.is_branch() || opcode.is_terminator() {
+            return (None, opcode.bits().to_vec());
+        }
+        match opcode.bits().split_first() {
+            Some((&dest, rest)) => (Some(dest), rest.to_vec()),
+            None => (None, Vec::new()),
+        }
+    }
+
+    /// Backward liveness dataflow over the recovered opcode table, to flag register writes that
+    /// are never read again (`DeadStore`) and opcodes no path from `initial_vm` can reach
+    /// (`UnreachableOpcode`) - likely mis-decoded opcodes rather than real obfuscation padding.
+    pub fn analyze_liveness(&self) -> Vec<DisassemblyWarning> {
+        let mut keys: Vec<u16> = self.opcodes.keys().copied().collect();
+        keys.sort_unstable();
+
+        let successors: FxHashMap<u16, Vec<u16>> = keys
+            .iter()
+            .map(|&key| (key, self.successors(key, &keys)))
+            .collect();
+
+        let defs_uses: FxHashMap<u16, (Option<u16>, Vec<u16>)> = keys
+            .iter()
+            .map(|&key| (key, Self::def_use(&self.opcodes[&key])))
+            .collect();
+
+        let mut live_in: FxHashMap<u16, HashSet<u16>> =
+            keys.iter().map(|&k| (k, HashSet::new())).collect();
+        let mut live_out: FxHashMap<u16, HashSet<u16>> =
+            keys.iter().map(|&k| (k, HashSet::new())).collect();
+
+        loop {
+            let mut changed = false;
+            for &key in keys.iter().rev() {
+                let mut out = HashSet::new();
+                for succ in &successors[&key] {
+                    out.extend(live_in[succ].iter().copied());
+                }
+
+                let (def, uses) = &defs_uses[&key];
+                let mut inn = out.clone();
+                if let Some(def) = def {
+                    inn.remove(def);
+                }
+                inn.extend(uses.iter().copied());
+
+                if out != live_out[&key] {
+                    live_out.insert(key, out);
+                    changed = true;
+                }
+                if inn != live_in[&key] {
+                    live_in.insert(key, inn);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let mut warnings = Vec::new();
+        for &key in &keys {
+            if let (Some(def), _) = &defs_uses[&key] {
+                if !live_out[&key].contains(def) {
+                    warnings.push(DisassemblyWarning::DeadStore {
+                        opcode: key,
+                        slot: *def,
+                    });
+                }
+            }
+        }
+
+        let reachable = self.reachable_from(self.initial_vm, &successors);
+        for &key in &keys {
+            if !reachable.contains(&key) {
+                warnings.push(DisassemblyWarning::UnreachableOpcode { opcode: key });
+            }
+        }
+
+        warnings
+    }
+
+    fn reachable_from(&self, entry: u16, successors: &FxHashMap<u16, Vec<u16>>) -> HashSet<u16> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![entry];
+        while let Some(key) = stack.pop() {
+            if !seen.insert(key) {
+                continue;
+            }
+            if let Some(succs) = successors.get(&key) {
+                stack.extend(succs.iter().copied());
+            }
+        }
+        seen
+    }
+}
+
+/// A finding from `RecursiveDisassembler::analyze_liveness`: either a register write whose
+/// value is never read on any path, or an opcode no path from the VM entry point reaches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisassemblyWarning {
+    DeadStore { opcode: u16, slot: u16 },
+    UnreachableOpcode { opcode: u16 },
+}
+
+impl std::fmt::Display for DisassemblyWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisassemblyWarning::DeadStore { opcode, slot } => {
+                write!(f, "opcode {opcode} writes slot {slot} but it is never read on any path")
+            }
+            DisassemblyWarning::UnreachableOpcode { opcode } => {
+                write!(f, "opcode {opcode} is unreachable from the VM entry point")
+            }
+        }
+    }
+}
+
+fn escape_dot_label(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
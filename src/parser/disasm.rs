@@ -0,0 +1,118 @@
+use rustc_hash::FxHashMap;
+use std::collections::{HashSet, VecDeque};
+
+/// One recovered instruction: the word offset it starts at, its opcode word, and whatever
+/// operand words followed it per `OpcodeTable`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instr {
+    pub offset: usize,
+    pub opcode: u16,
+    pub operands: Vec<u16>,
+}
+
+/// What `decode` needs to know about one opcode word: how many operand words follow it, which
+/// operand (if any) carries an absolute target word offset for control flow, and whether no path
+/// falls through past it - the same three facts `RecursiveDisassembler`'s `successors`/`def_use`
+/// need for the AST-recovered `Opcode` table, kept here for the raw word-level decode instead.
+#[derive(Debug, Clone)]
+pub struct OpcodeDef {
+    pub operand_count: usize,
+    pub branch_operand: Option<usize>,
+    pub is_terminator: bool,
+}
+
+/// Maps opcode words to their `OpcodeDef`. Callers supply this rather than it being fixed in the
+/// crate, since the actual VM word values `ScriptVisitor`'s decompressed payload uses haven't
+/// been statically recovered the way `magic_bits::Opcode` was - this is a configurable stand-in
+/// until they are.
+pub type OpcodeTable = FxHashMap<u16, OpcodeDef>;
+
+/// Decodes `words` into a linear instruction listing: starting at offset 0, reads one opcode word
+/// plus its operands per `table` and advances past them. A word missing from `table` is treated
+/// as a single, operand-less unknown instruction so gaps in an incomplete opcode table don't
+/// desync the rest of the stream - it just shows up as unclassified in the listing.
+pub fn decode(words: &[u16], table: &OpcodeTable) -> Vec<Instr> {
+    let mut out = Vec::new();
+    let mut offset = 0usize;
+    while offset < words.len() {
+        let opcode = words[offset];
+        let operand_count = table.get(&opcode).map_or(0, |def| def.operand_count);
+        let end = (offset + 1 + operand_count).min(words.len());
+        let operands = words[offset + 1..end].to_vec();
+        out.push(Instr {
+            offset,
+            opcode,
+            operands,
+        });
+        offset = end;
+    }
+    out
+}
+
+/// CFG successors of one decoded instruction: the resolved branch target (if `table` marks this
+/// opcode as a branch and the target operand is present), plus the fall-through to
+/// `next_offset` - unless `table` marks it a terminator, in which case it has none. Mirrors the
+/// edge rules `RecursiveDisassembler::successors` draws for the AST-recovered opcode table.
+fn successors(instr: &Instr, table: &OpcodeTable, next_offset: Option<usize>) -> Vec<usize> {
+    let Some(def) = table.get(&instr.opcode) else {
+        return next_offset.into_iter().collect();
+    };
+    if def.is_terminator {
+        return Vec::new();
+    }
+
+    let mut succs = Vec::new();
+    if let Some(idx) = def.branch_operand {
+        if let Some(&target) = instr.operands.get(idx) {
+            succs.push(target as usize);
+        }
+    }
+    succs.extend(next_offset);
+    succs
+}
+
+/// Worklist BFS from `entry` over `successors`, computing which decoded instructions are
+/// actually reachable. Obfuscators commonly interleave dead padding between live instructions at
+/// offsets nothing jumps to; this is what tells that padding apart from real code.
+pub fn reachable(instrs: &[Instr], table: &OpcodeTable, entry: usize) -> HashSet<usize> {
+    let offset_index: FxHashMap<usize, usize> = instrs
+        .iter()
+        .enumerate()
+        .map(|(i, instr)| (instr.offset, i))
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(entry);
+
+    while let Some(offset) = queue.pop_front() {
+        if !seen.insert(offset) {
+            continue;
+        }
+        let Some(&idx) = offset_index.get(&offset) else {
+            continue;
+        };
+        let next_offset = instrs.get(idx + 1).map(|next| next.offset);
+        for succ in successors(&instrs[idx], table, next_offset) {
+            if !seen.contains(&succ) {
+                queue.push_back(succ);
+            }
+        }
+    }
+
+    seen
+}
+
+/// Decodes `words` and splits the result into the full linear listing plus the subset reachable
+/// from `entry` - the pruned listing is the readable program a user actually wants; the full one
+/// stays available for inspecting exactly what got dropped as dead.
+pub fn disassemble(words: &[u16], table: &OpcodeTable, entry: usize) -> (Vec<Instr>, Vec<Instr>) {
+    let all = decode(words, table);
+    let live = reachable(&all, table, entry);
+    let pruned = all
+        .iter()
+        .filter(|instr| live.contains(&instr.offset))
+        .cloned()
+        .collect();
+    (all, pruned)
+}
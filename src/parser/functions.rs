@@ -1,77 +1,318 @@
 use oxc_ast::ast::{
-    ArrayExpressionElement, AssignmentExpression, AssignmentTarget, Expression, Function,
-    VariableDeclaration,
+    ArrayExpressionElement, AssignmentExpression, AssignmentTarget, DoWhileStatement, Expression,
+    ForStatement, Function, IfStatement, Statement, VariableDeclaration, WhileStatement,
 };
 use oxc_ast_visit::{
-    walk::{walk_assignment_expression, walk_function, walk_variable_declaration},
+    walk::{
+        walk_assignment_expression, walk_do_while_statement, walk_for_statement, walk_function,
+        walk_statement, walk_variable_declaration, walk_while_statement,
+    },
     Visit,
 };
 use oxc_semantic::ScopeFlags;
 use rustc_hash::FxHashMap;
+use std::collections::HashSet;
+
+/// A JS-accurate tagged constant value. `resolve_expr` used to collapse everything to `f64`
+/// (eagerly `parse::<f64>()`-ing strings), which mis-folds or silently drops anything that
+/// isn't pure arithmetic. Carrying the tag through lets the operators apply the right JS
+/// coercion rules instead.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JsValue {
+    Num(f64),
+    Str(String),
+    Bool(bool),
+}
+
+impl JsValue {
+    /// ToNumber.
+    pub(crate) fn to_number(&self) -> f64 {
+        match self {
+            JsValue::Num(n) => *n,
+            JsValue::Bool(b) => if *b { 1.0 } else { 0.0 },
+            JsValue::Str(s) => {
+                let trimmed = s.trim();
+                if trimmed.is_empty() { 0.0 } else { trimmed.parse::<f64>().unwrap_or(f64::NAN) }
+            }
+        }
+    }
+
+    /// JS truthiness, used by `!`, `&&`, `||` and `if`/conditional tests.
+    pub(crate) fn to_bool(&self) -> bool {
+        match self {
+            JsValue::Num(n) => *n != 0.0 && !n.is_nan(),
+            JsValue::Bool(b) => *b,
+            JsValue::Str(s) => !s.is_empty(),
+        }
+    }
+
+    fn to_js_string(&self) -> String {
+        match self {
+            JsValue::Num(n) => n.to_string(),
+            JsValue::Bool(b) => b.to_string(),
+            JsValue::Str(s) => s.clone(),
+        }
+    }
+}
+
+/// Applies a binary operator with JS coercion rules: `+` concatenates when either side is a
+/// string and otherwise adds numerically, comparison/bitwise operators apply ToNumber to both
+/// sides first.
+pub(crate) fn eval_binary_op_val(op: &str, l: &JsValue, r: &JsValue) -> Option<JsValue> {
+    match op {
+        "+" => {
+            if matches!(l, JsValue::Str(_)) || matches!(r, JsValue::Str(_)) {
+                Some(JsValue::Str(format!("{}{}", l.to_js_string(), r.to_js_string())))
+            } else {
+                Some(JsValue::Num(l.to_number() + r.to_number()))
+            }
+        }
+        "-" | "*" | "/" | "%" | "&" | "|" | "^" | "<<" | ">>" => {
+            eval_binary_op(op, l.to_number(), r.to_number()).map(JsValue::Num)
+        }
+        ">" => Some(JsValue::Bool(l.to_number() > r.to_number())),
+        "<" => Some(JsValue::Bool(l.to_number() < r.to_number())),
+        ">=" => Some(JsValue::Bool(l.to_number() >= r.to_number())),
+        "<=" => Some(JsValue::Bool(l.to_number() <= r.to_number())),
+        "==" | "===" => Some(JsValue::Bool(l.to_number() == r.to_number())),
+        "!=" | "!==" => Some(JsValue::Bool(l.to_number() != r.to_number())),
+        _ => None,
+    }
+}
+
+/// Applies a unary operator with JS coercion rules: `!` uses truthiness, everything else
+/// (`-`, `+`, `~`) applies ToNumber.
+pub(crate) fn eval_unary_op_val(op: &str, v: &JsValue) -> Option<JsValue> {
+    match op {
+        "!" => Some(JsValue::Bool(!v.to_bool())),
+        _ => eval_unary_op(op, v.to_number()).map(JsValue::Num),
+    }
+}
+
+/// A lattice value for scope-and-control-flow-aware constant propagation.
+///
+/// `Unknown` means "not assigned yet on this path", `Const` means every path that reaches
+/// this point agrees on the value, and `Top` means two paths disagreed (or the variable is
+/// reassigned inside a loop without being provably invariant) — once a variable is `Top`,
+/// `resolve_expr` stops folding it rather than silently returning a stale value.
+#[derive(Debug, Clone, PartialEq)]
+enum Lattice {
+    Unknown,
+    Const(JsValue),
+    Top,
+}
+
+impl Lattice {
+    fn join(self, other: Lattice) -> Lattice {
+        match (self, other) {
+            (Lattice::Unknown, x) | (x, Lattice::Unknown) => x,
+            (Lattice::Const(a), Lattice::Const(b)) => {
+                if a == b { Lattice::Const(a) } else { Lattice::Top }
+            }
+            (Lattice::Top, _) | (_, Lattice::Top) => Lattice::Top,
+        }
+    }
+}
+
+type Scope<'a> = FxHashMap<&'a str, Lattice>;
+
+/// A recognized constant array literal (`var tbl = [1, 2, 3]`), tracked so `resolve_expr`
+/// can fold `tbl[i]` reads the same way it folds scalar constants. Each element is itself
+/// only `Some` when it resolved to a constant; the length is always known so out-of-range
+/// indices fail cleanly instead of producing garbage.
+#[derive(Debug, Clone, Default)]
+struct ArrayConst {
+    elements: Vec<Option<JsValue>>,
+}
+
+type ArrayScope<'a> = FxHashMap<&'a str, ArrayConst>;
 
-#[derive(Default)]
 pub struct FindFunctions<'a> {
     last_function_name: &'a str,
-    is_big_function: bool, 
+    is_big_function: bool,
     pub key: u16,
     pub constants: u16,
-    pub function_with_opcodes: &'a str, 
+    pub function_with_opcodes: &'a str,
     pub functions: FxHashMap<&'a str, u16>,
-    pub variables: FxHashMap<&'a str, f64>,
+    /// Scope-stack of constant-propagation lattices, innermost scope last. Pushed/popped on
+    /// function and block entry so a reassignment in one branch, or a shadowed name in a
+    /// nested scope, can no longer poison the value seen by sibling scopes.
+    scopes: Vec<Scope<'a>>,
+    /// Mirrors `scopes`, but for recognized constant array literals.
+    array_scopes: Vec<ArrayScope<'a>>,
+    /// Depth of enclosing `for`/`while`/`do-while` loops; assignments made while this is
+    /// nonzero are only kept as `Const` if every write agrees (i.e. are provably loop
+    /// invariant), otherwise they are immediately widened to `Top`.
+    loop_depth: u32,
+}
+
+impl<'a> Default for FindFunctions<'a> {
+    fn default() -> Self {
+        Self {
+            last_function_name: "",
+            is_big_function: false,
+            key: 0,
+            constants: 0,
+            function_with_opcodes: "",
+            functions: FxHashMap::default(),
+            // Seed one base/module scope so top-level assignments (outside any function
+            // body) still have somewhere to land.
+            scopes: vec![Scope::default()],
+            array_scopes: vec![ArrayScope::default()],
+            loop_depth: 0,
+        }
+    }
 }
 
 impl<'a> FindFunctions<'a> {
-    fn resolve_expr(&self, expr: &Expression) -> Option<f64> {
+    fn push_scope(&mut self) {
+        self.scopes.push(Scope::default());
+        self.array_scopes.push(ArrayScope::default());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+        self.array_scopes.pop();
+    }
+
+    fn lookup(&self, name: &str) -> Lattice {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).cloned())
+            .unwrap_or(Lattice::Unknown)
+    }
+
+    fn lookup_array(&self, name: &str) -> Option<&ArrayConst> {
+        self.array_scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn declare_array(&mut self, name: &'a str, value: ArrayConst) {
+        if let Some(scope) = self.array_scopes.last_mut() {
+            scope.insert(name, value);
+        }
+    }
+
+    /// Resolves `expr` down to a known array literal, peeling parens and the "static member"
+    /// chain (`tbl.nested[..]`) wrappers an obfuscator might put around the plain identifier.
+    fn resolve_array(&self, expr: &Expression) -> Option<&ArrayConst> {
+        match expr {
+            Expression::Identifier(ident) => self.lookup_array(ident.name.as_str()),
+            Expression::ParenthesizedExpression(paren) => self.resolve_array(&paren.expression),
+            Expression::StaticMemberExpression(member) => self.resolve_array(&member.object),
+            _ => None,
+        }
+    }
+
+    /// Folds `tbl[index]` once `tbl` is a known array literal: the index itself goes through
+    /// the normal constant-folding logic, and anything non-integer, negative, or out of
+    /// `0..len` fails cleanly rather than returning garbage.
+    fn resolve_array_index(&self, object: &Expression, index: &Expression) -> Option<JsValue> {
+        let array = self.resolve_array(object)?;
+        let idx = self.resolve_expr_num(index)?;
+        if idx.is_nan() || idx.fract() != 0.0 || idx < 0.0 {
+            return None;
+        }
+        let idx = idx as usize;
+        array.elements.get(idx).cloned().flatten()
+    }
+
+    /// Declares a brand-new binding (`var`/`let`/`const`) in the innermost scope, shadowing
+    /// any outer binding of the same name for the rest of that scope's lifetime.
+    fn declare(&mut self, name: &'a str, value: Lattice) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, value);
+        }
+    }
+
+    /// Records a reassignment: writes into whichever scope already owns `name`, falling back
+    /// to the innermost scope when the identifier hasn't been seen before (obfuscators reuse
+    /// bare names freely without `var`/`let`).
+    fn assign(&mut self, name: &'a str, value: Lattice) {
+        let resolved = if self.loop_depth > 0 {
+            match self.lookup(name) {
+                Lattice::Unknown => value,
+                existing => existing.join(value),
+            }
+        } else {
+            value
+        };
+
+        for scope in self.scopes.iter_mut().rev() {
+            if scope.contains_key(name) {
+                scope.insert(name, resolved);
+                return;
+            }
+        }
+        self.declare(name, resolved);
+    }
+
+    fn resolve_expr(&self, expr: &Expression) -> Option<JsValue> {
         match expr {
-            Expression::NumericLiteral(lit) => Some(lit.value),
-            Expression::StringLiteral(lit) => lit.value.parse::<f64>().ok(),
-            Expression::Identifier(ident) => self.variables.get(ident.name.as_str()).copied(),
+            Expression::NumericLiteral(lit) => Some(JsValue::Num(lit.value)),
+            Expression::StringLiteral(lit) => Some(JsValue::Str(lit.value.to_string())),
+            Expression::BooleanLiteral(lit) => Some(JsValue::Bool(lit.value)),
+            Expression::Identifier(ident) => match self.lookup(ident.name.as_str()) {
+                Lattice::Const(v) => Some(v),
+                _ => None,
+            },
             Expression::ParenthesizedExpression(paren) => self.resolve_expr(&paren.expression),
             Expression::SequenceExpression(seq) => seq.expressions.last().and_then(|e| self.resolve_expr(e)),
             Expression::UnaryExpression(unary) => {
                 let val = self.resolve_expr(&unary.argument)?;
-                match unary.operator.as_str() {
-                    "-" => Some(-val), "+" => Some(val), "~" => Some((!(val as i64)) as f64),
-                    "!" => Some(if val == 0.0 { 1.0 } else { 0.0 }), _ => None,
-                }
+                eval_unary_op_val(unary.operator.as_str(), &val)
             },
             Expression::BinaryExpression(bin) => {
                 let left = self.resolve_expr(&bin.left);
                 let right = self.resolve_expr(&bin.right);
                 match (left, right) {
-                    (Some(l), Some(r)) => match bin.operator.as_str() {
-                        "+" => Some(l + r), "-" => Some(l - r), "*" => Some(l * r), "/" => Some(l / r),
-                        "%" => Some(l % r), "&" => Some((l as i64 & r as i64) as f64), "|" => Some((l as i64 | r as i64) as f64),
-                        "^" => Some((l as i64 ^ r as i64) as f64), "<<" => Some(((l as i64) << (r as i64)) as f64),
-                        ">>" => Some(((l as i64) >> (r as i64)) as f64), _ => None,
-                    },
-                    _ => None 
+                    (Some(l), Some(r)) => eval_binary_op_val(bin.operator.as_str(), &l, &r),
+                    _ => None
                 }
             },
             Expression::LogicalExpression(logic) => {
                 let left = self.resolve_expr(&logic.left);
-                let right = self.resolve_expr(&logic.right);
                 match logic.operator.as_str() {
-                    "||" => if let Some(l) = left { if l != 0.0 && !l.is_nan() { Some(l) } else { right } } else { right },
-                    "&&" => if let Some(l) = left { if l == 0.0 || l.is_nan() { Some(l) } else { right } } else { None },
-                    "??" => if left.is_some() { left } else { right }, _ => None
+                    "||" => match &left {
+                        Some(l) if l.to_bool() => left,
+                        _ => self.resolve_expr(&logic.right),
+                    },
+                    "&&" => match &left {
+                        Some(l) if !l.to_bool() => left,
+                        Some(_) => self.resolve_expr(&logic.right),
+                        None => None,
+                    },
+                    "??" => if left.is_some() { left } else { self.resolve_expr(&logic.right) },
+                    _ => None,
                 }
             },
             Expression::ConditionalExpression(cond) => {
                 let test = self.resolve_expr(&cond.test);
-                if let Some(t) = test {
-                    if t != 0.0 && !t.is_nan() { self.resolve_expr(&cond.consequent) } else { self.resolve_expr(&cond.alternate) }
-                } else { None }
+                match test {
+                    Some(t) if t.to_bool() => self.resolve_expr(&cond.consequent),
+                    Some(_) => self.resolve_expr(&cond.alternate),
+                    None => None,
+                }
+            }
+            Expression::ComputedMemberExpression(member) => {
+                self.resolve_array_index(&member.object, &member.expression)
             }
             _ => None,
         }
     }
 
+    /// Thin numeric wrapper over `resolve_expr` for call sites (`resolve_index`, the opcode
+    /// register/constants-slot bookkeeping) that only ever care about the numeric value and
+    /// apply ToNumber themselves.
+    fn resolve_expr_num(&self, expr: &Expression) -> Option<f64> {
+        self.resolve_expr(expr).map(|v| v.to_number())
+    }
+
     fn resolve_index(&self, expr: &Expression) -> Option<u16> {
-        if let Some(val) = self.resolve_expr(expr) { return Some(val as u16); }
+        if let Some(val) = self.resolve_expr_num(expr) { return Some(val as u16); }
         if let Expression::BinaryExpression(bin) = expr {
-            let left = self.resolve_expr(&bin.left);
-            let right = self.resolve_expr(&bin.right);
+            let left = self.resolve_expr_num(&bin.left);
+            let right = self.resolve_expr_num(&bin.right);
             match (left, right) {
                 (Some(val), None) | (None, Some(val)) => return Some(val as u16),
                 _ => {}
@@ -100,15 +341,77 @@ impl<'a> FindFunctions<'a> {
     }
 }
 
+/// Applies a unary operator the same way `resolve_expr` does; the numeric-coercion fallback
+/// `eval_unary_op_val` uses once it's already applied ToNumber to a non-`!` operand.
+pub(crate) fn eval_unary_op(op: &str, val: f64) -> Option<f64> {
+    match op {
+        "-" => Some(-val),
+        "+" => Some(val),
+        "~" => Some((!(val as i64)) as f64),
+        "!" => Some(if val == 0.0 { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+}
+
+/// Applies a binary operator the same way `resolve_expr` does; the numeric-coercion fallback
+/// `eval_binary_op_val` uses for the operators that don't need string-concat/JS-coercion
+/// special-casing.
+pub(crate) fn eval_binary_op(op: &str, l: f64, r: f64) -> Option<f64> {
+    match op {
+        "+" => Some(l + r),
+        "-" => Some(l - r),
+        "*" => Some(l * r),
+        "/" => Some(l / r),
+        "%" => Some(l % r),
+        "&" => Some((l as i64 & r as i64) as f64),
+        "|" => Some((l as i64 | r as i64) as f64),
+        "^" => Some((l as i64 ^ r as i64) as f64),
+        "<<" => Some(((l as i64) << (r as i64)) as f64),
+        ">>" => Some(((l as i64) >> (r as i64)) as f64),
+        _ => None,
+    }
+}
+
+/// Joins two post-branch scope stacks (same shape as `baseline`, since balanced block
+/// push/pop keeps stack depth in sync across both arms of an `if`) back into a single stack:
+/// anything assigned the same way on both arms stays `Const`, anything that diverges widens
+/// to `Top`.
+fn join_scope_stacks<'a>(
+    baseline: &[Scope<'a>],
+    then_branch: &[Scope<'a>],
+    else_branch: &[Scope<'a>],
+) -> Vec<Scope<'a>> {
+    baseline
+        .iter()
+        .enumerate()
+        .map(|(i, base_scope)| {
+            let mut merged = base_scope.clone();
+            let then_scope = then_branch.get(i);
+            let else_scope = else_branch.get(i);
+            let keys: HashSet<&str> = then_scope
+                .into_iter()
+                .flat_map(|m| m.keys().copied())
+                .chain(else_scope.into_iter().flat_map(|m| m.keys().copied()))
+                .collect();
+            for key in keys {
+                let then_val = then_scope.and_then(|m| m.get(key)).cloned().unwrap_or(Lattice::Unknown);
+                let else_val = else_scope.and_then(|m| m.get(key)).cloned().unwrap_or(Lattice::Unknown);
+                merged.insert(key, then_val.join(else_val));
+            }
+            merged
+        })
+        .collect()
+}
+
 impl<'a> Visit<'a> for FindFunctions<'a> {
     fn visit_function(&mut self, node: &Function<'a>, flags: ScopeFlags) {
         // Logique "Silver Bullet" : Anonyme ou pas, si > 50 lignes, c'est VM_ENTRY
         let mut name = node.id.as_ref().map(|id| id.name.as_str()).unwrap_or("");
-        
+
         if let Some(body) = &node.body {
             if body.statements.len() > 50 {
                 self.is_big_function = true;
-                name = "VM_ENTRY"; 
+                name = "VM_ENTRY";
                 // eprintln!("[INFO] VM Detected (>50 lines), forcing name 'VM_ENTRY'");
             } else {
                 self.is_big_function = false;
@@ -120,16 +423,80 @@ impl<'a> Visit<'a> for FindFunctions<'a> {
         self.last_function_name = name;
         if self.is_big_function { self.function_with_opcodes = name; }
 
+        self.push_scope();
         walk_function(self, node, flags);
+        self.pop_scope();
+    }
+
+    fn visit_if_statement(&mut self, stmt: &IfStatement<'a>) {
+        self.visit_expression(&stmt.test);
+
+        let baseline = self.scopes.clone();
+
+        self.push_scope();
+        self.visit_statement(&stmt.consequent);
+        self.pop_scope();
+        let after_then = std::mem::replace(&mut self.scopes, baseline.clone());
+
+        if let Some(alternate) = &stmt.alternate {
+            self.push_scope();
+            self.visit_statement(alternate);
+            self.pop_scope();
+        }
+        let after_else = std::mem::replace(&mut self.scopes, baseline.clone());
+
+        self.scopes = join_scope_stacks(&baseline, &after_then, &after_else);
+    }
+
+    fn visit_for_statement(&mut self, node: &ForStatement<'a>) {
+        self.loop_depth += 1;
+        self.push_scope();
+        walk_for_statement(self, node);
+        self.pop_scope();
+        self.loop_depth -= 1;
+    }
+
+    fn visit_while_statement(&mut self, node: &WhileStatement<'a>) {
+        self.loop_depth += 1;
+        self.push_scope();
+        walk_while_statement(self, node);
+        self.pop_scope();
+        self.loop_depth -= 1;
+    }
+
+    fn visit_do_while_statement(&mut self, node: &DoWhileStatement<'a>) {
+        self.loop_depth += 1;
+        self.push_scope();
+        walk_do_while_statement(self, node);
+        self.pop_scope();
+        self.loop_depth -= 1;
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement<'a>) {
+        if let Statement::BlockStatement(_) = stmt {
+            self.push_scope();
+            walk_statement(self, stmt);
+            self.pop_scope();
+        } else {
+            walk_statement(self, stmt);
+        }
     }
 
     fn visit_variable_declaration(&mut self, decl: &VariableDeclaration<'a>) {
         for declarator in &decl.declarations {
             if let Some(init) = &declarator.init {
                 if let oxc_ast::ast::BindingPatternKind::BindingIdentifier(ident) = &declarator.id.kind {
-                    if let Some(val) = self.resolve_expr(init) {
-                        self.variables.insert(ident.name.as_str(), val);
+                    if let Expression::ArrayExpression(array) = init {
+                        let elements = array
+                            .elements
+                            .iter()
+                            .map(|el| el.as_expression().and_then(|e| self.resolve_expr(e)))
+                            .collect();
+                        self.declare_array(ident.name.as_str(), ArrayConst { elements });
                     }
+
+                    let value = self.resolve_expr(init).map(Lattice::Const).unwrap_or(Lattice::Top);
+                    self.declare(ident.name.as_str(), value);
                 }
             }
         }
@@ -138,9 +505,8 @@ impl<'a> Visit<'a> for FindFunctions<'a> {
 
     fn visit_assignment_expression(&mut self, node: &AssignmentExpression<'a>) {
         if let AssignmentTarget::AssignmentTargetIdentifier(ident) = &node.left {
-            if let Some(val) = self.resolve_expr(&node.right) {
-                self.variables.insert(ident.name.as_str(), val);
-            }
+            let value = self.resolve_expr(&node.right).map(Lattice::Const).unwrap_or(Lattice::Top);
+            self.assign(ident.name.as_str(), value);
         }
 
         if self.is_big_function {
@@ -168,4 +534,4 @@ impl<'a> Visit<'a> for FindFunctions<'a> {
         }
         walk_assignment_expression(self, node);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,222 @@
+use crate::parser::vm::ScriptVisitor;
+use oxc_allocator::Allocator;
+use oxc_ast_visit::Visit;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn random_string(rng: &mut StdRng, len: usize) -> String {
+    (0..len)
+        .map(|_| ALPHABET[rng.random_range(0..ALPHABET.len())] as char)
+        .collect()
+}
+
+/// One synthesized obfuscated-script source string, tagged with the seed that produced it so a
+/// failure can be reproduced later by re-seeding with `seed` alone instead of replaying the
+/// whole outer RNG stream.
+#[derive(Debug, Clone)]
+pub struct FuzzCase {
+    pub seed: u64,
+    pub source: String,
+}
+
+/// Generates one candidate source: decoy statements `ScriptVisitor` should ignore (a
+/// three-colon-shaped init-argument regex literal, 64- and 66-char near-miss charset strings
+/// that must NOT be mistaken for the real 65-char one), an optional genuine 65-char charset, and
+/// nested nested VM-payload-shaped calls - one bucketed as `initial_vm` length, one as `main_vm`
+/// length, sometimes with a second "key" argument, sometimes wrapped in an outer call.
+fn generate_source(rng: &mut StdRng) -> String {
+    let mut statements = Vec::new();
+
+    statements.push(format!(
+        "var initArg = \"/{}:{}:{}/\";",
+        random_string(rng, 8),
+        random_string(rng, 8),
+        random_string(rng, 8)
+    ));
+
+    for near_miss_len in [64usize, 66usize] {
+        let mut value = random_string(rng, near_miss_len - 3);
+        value.push('$');
+        value.push('-');
+        value.push('+');
+        statements.push(format!(
+            "var nearMissCharset{near_miss_len} = \"{value}\";"
+        ));
+    }
+
+    if rng.random_bool(0.5) {
+        let mut value = random_string(rng, 62);
+        value.push('$');
+        value.push('-');
+        value.push('+');
+        statements.push(format!("var charset = \"{value}\";"));
+    }
+
+    let initial_len = rng.random_range(301..900);
+    let main_len = if rng.random_bool(0.7) {
+        rng.random_range(1000..3000)
+    } else {
+        rng.random_range(301..999)
+    };
+
+    let initial_payload = random_string(rng, initial_len);
+    let main_payload = random_string(rng, main_len);
+
+    let callee = format!("decode{}", rng.random_range(0..1000));
+    let key_arg = if rng.random_bool(0.5) {
+        format!(", \"{}\"", random_string(rng, 8))
+    } else {
+        String::new()
+    };
+    statements.push(format!("{callee}(\"{initial_payload}\"{key_arg});"));
+
+    let outer = format!("wrap{}", rng.random_range(0..1000));
+    statements.push(format!("{outer}({callee}(\"{main_payload}\"));"));
+
+    statements.join("\n")
+}
+
+/// An invariant `ScriptVisitor` must hold for any generated source, checked by `run` against
+/// every case. `check` returns `true` when the invariant holds (including vacuously, when the
+/// field it's about wasn't populated at all).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Invariant {
+    MainVmLenAtLeast1000,
+    InitArgumentShape,
+    CharsetExactly65,
+}
+
+const ALL_INVARIANTS: &[Invariant] = &[
+    Invariant::MainVmLenAtLeast1000,
+    Invariant::InitArgumentShape,
+    Invariant::CharsetExactly65,
+];
+
+impl Invariant {
+    fn check(&self, visitor: &ScriptVisitor) -> bool {
+        match self {
+            Invariant::MainVmLenAtLeast1000 => {
+                visitor.main_vm().is_none_or(|c| c.value.len() >= 1000)
+            }
+            Invariant::InitArgumentShape => visitor.init_argument.as_ref().is_none_or(|arg| {
+                arg.starts_with('/') && arg.ends_with('/') && arg.split(':').count() == 3
+            }),
+            Invariant::CharsetExactly65 => visitor
+                .compressor_charset
+                .as_ref()
+                .is_none_or(|cs| cs.chars().count() == 65),
+        }
+    }
+}
+
+/// A case `run` found breaking an invariant (or panicking outright - `violated` is `None` and
+/// `panic_message` is set in that case instead).
+#[derive(Debug)]
+pub struct FuzzFailure {
+    pub case: FuzzCase,
+    pub violated: Option<Invariant>,
+    pub panic_message: Option<String>,
+}
+
+fn run_case(case: &FuzzCase) -> Result<ScriptVisitor, ()> {
+    let allocator = Allocator::default();
+    let source_type = SourceType::default();
+    let ret = Parser::new(&allocator, &case.source, source_type).parse();
+    if ret.program.body.is_empty() {
+        return Err(());
+    }
+    let mut visitor = ScriptVisitor::default();
+    visitor.visit_program(&ret.program);
+    Ok(visitor)
+}
+
+/// Generates up to `iterations` randomized cases from `seed` and runs `ScriptVisitor` over each,
+/// checking every `Invariant`. A panic inside parsing/visiting counts as a failure on its own,
+/// caught via `catch_unwind` rather than taking the whole fuzz run down with it. Returns the
+/// first failure found, shrunk to a smaller reproduction, or `None` if every case passed.
+pub fn run(seed: u64, iterations: usize) -> Option<FuzzFailure> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    for _ in 0..iterations {
+        let case_seed = rng.random();
+        let mut case_rng = StdRng::seed_from_u64(case_seed);
+        let case = FuzzCase {
+            seed: case_seed,
+            source: generate_source(&mut case_rng),
+        };
+
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| run_case(&case))) {
+            Ok(Ok(visitor)) => {
+                if let Some(violated) = ALL_INVARIANTS.iter().find(|inv| !inv.check(&visitor)) {
+                    return Some(shrink(case, violated.clone()));
+                }
+            }
+            Ok(Err(())) => {
+                return Some(FuzzFailure {
+                    case,
+                    violated: None,
+                    panic_message: None,
+                });
+            }
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "panic with non-string payload".to_string());
+                return Some(FuzzFailure {
+                    case,
+                    violated: None,
+                    panic_message: Some(message),
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// Trims `case`'s generated source one statement line at a time, keeping the removal whenever
+/// the same `violated` invariant still fails without it, until no single line can be dropped any
+/// further - a source-text stand-in for the usual "shrink the generated tree" step, since these
+/// cases are synthesized as JS text (via `oxc_parser`) rather than built as literal AST nodes.
+fn shrink(case: FuzzCase, violated: Invariant) -> FuzzFailure {
+    let mut lines: Vec<String> = case.source.lines().map(str::to_string).collect();
+
+    loop {
+        let mut shrunk_at = None;
+        for i in 0..lines.len() {
+            let mut candidate_lines = lines.clone();
+            candidate_lines.remove(i);
+            let candidate = FuzzCase {
+                seed: case.seed,
+                source: candidate_lines.join("\n"),
+            };
+            if let Ok(Ok(visitor)) =
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| run_case(&candidate)))
+            {
+                if !violated.check(&visitor) {
+                    shrunk_at = Some(candidate_lines);
+                    break;
+                }
+            }
+        }
+        match shrunk_at {
+            Some(candidate_lines) => lines = candidate_lines,
+            None => break,
+        }
+    }
+
+    FuzzFailure {
+        case: FuzzCase {
+            seed: case.seed,
+            source: lines.join("\n"),
+        },
+        violated: Some(violated),
+        panic_message: None,
+    }
+}
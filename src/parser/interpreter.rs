@@ -0,0 +1,401 @@
+use crate::parser::functions::{eval_binary_op_val, eval_unary_op_val, JsValue};
+use crate::parser::magic_bits::{HeapType, LiteralType, Opcode};
+use rustc_hash::FxHashMap;
+
+/// A register value. Strictly a superset of `functions::JsValue` - the scalar arithmetic
+/// opcodes (`Binary`/`Unary`) only ever read/write `Scalar`, but `NewObject`/`NewArray`/
+/// `ArrayPush`/`Heap` need something richer to model, so this wraps `JsValue` rather than
+/// duplicating it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Undefined,
+    Null,
+    Scalar(JsValue),
+    Array(Vec<Value>),
+    Object(FxHashMap<String, Value>),
+}
+
+impl Value {
+    fn as_js(&self) -> Option<JsValue> {
+        match self {
+            Value::Scalar(v) => Some(v.clone()),
+            _ => None,
+        }
+    }
+
+    fn object_key(&self) -> String {
+        match self {
+            Value::Scalar(JsValue::Str(s)) => s.clone(),
+            Value::Scalar(JsValue::Num(n)) => n.to_string(),
+            Value::Scalar(JsValue::Bool(b)) => b.to_string(),
+            Value::Undefined => "undefined".to_string(),
+            Value::Null => "null".to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Why `OpcodeVM::run` stopped short of a clean `Halt`. Distinct from `anyhow::Error` (like
+/// `task_client::Error`/`challenge::Error`) since callers need to match on it, not just log it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VmError {
+    /// No opcode recovered at this program counter - either the disassembly is incomplete or
+    /// execution wandered into data (e.g. a mis-resolved jump target).
+    UnknownOpcode(u16),
+    /// A `Jump`/`JumpIf` operand didn't XOR-decode to a register any recovered opcode occupies
+    /// (see `RecursiveDisassembler::resolve_target`, which hits the same ambiguity).
+    UnresolvedJumpTarget(u16),
+    /// `Throw` executed - carries the thrown value's best-effort string rendering.
+    Thrown(String),
+    /// Execution exceeded the caller-supplied step budget, so a real infinite loop (or a
+    /// mis-recovered back-edge) can't hang the caller forever.
+    StepLimitExceeded,
+}
+
+impl std::fmt::Display for VmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VmError::UnknownOpcode(pc) => write!(f, "no recovered opcode at register {pc}"),
+            VmError::UnresolvedJumpTarget(raw) => {
+                write!(f, "jump operand {raw} did not resolve to a known opcode")
+            }
+            VmError::Thrown(msg) => write!(f, "script threw: {msg}"),
+            VmError::StepLimitExceeded => write!(f, "exceeded step budget without halting"),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+/// Executes the opcode table `OpcodeParser` recovers as an actual register machine, so the
+/// devirtualizer can confirm its own classification by running it rather than only printing it.
+///
+/// Registers are a flat `Vec<Value>`, growing on demand as indices are first written. The
+/// program counter is a register key (`u16`) rather than an array
+/// index, mirroring how `opcodes: FxHashMap<u16, Opcode>` is itself keyed - "next instruction"
+/// for a straight-line opcode means "next key in sorted order", the same fallthrough
+/// convention `RecursiveDisassembler::successors` uses for its CFG edges.
+pub struct OpcodeVM<'a> {
+    opcodes: &'a FxHashMap<u16, Opcode>,
+    keys: Vec<u16>,
+    key: u16,
+    constants: Vec<Value>,
+    registers: Vec<Value>,
+    heap: FxHashMap<u16, Value>,
+    pc: u16,
+}
+
+impl<'a> OpcodeVM<'a> {
+    pub fn new(
+        opcodes: &'a FxHashMap<u16, Opcode>,
+        constants: Vec<Value>,
+        key: u16,
+        entry: u16,
+    ) -> Self {
+        let mut keys: Vec<u16> = opcodes.keys().copied().collect();
+        keys.sort_unstable();
+
+        Self {
+            opcodes,
+            keys,
+            key,
+            constants,
+            registers: Vec::new(),
+            heap: FxHashMap::default(),
+            pc: entry,
+        }
+    }
+
+    fn register(&self, idx: u16) -> Value {
+        self.registers.get(idx as usize).cloned().unwrap_or(Value::Undefined)
+    }
+
+    fn set_register(&mut self, idx: u16, value: Value) {
+        let idx = idx as usize;
+        if idx >= self.registers.len() {
+            self.registers.resize(idx + 1, Value::Undefined);
+        }
+        self.registers[idx] = value;
+    }
+
+    /// Same XOR-against-key decode `RecursiveDisassembler::resolve_target` uses: the raw bit
+    /// captured for a jump operand isn't the target register directly, it's the target
+    /// register masked against the recovered key.
+    fn resolve_jump(&self, raw: u16) -> Result<u16, VmError> {
+        let target = raw ^ self.key;
+        if self.opcodes.contains_key(&target) {
+            Ok(target)
+        } else {
+            Err(VmError::UnresolvedJumpTarget(raw))
+        }
+    }
+
+    fn fallthrough(&self) -> Option<u16> {
+        self.keys.iter().copied().find(|&k| k > self.pc)
+    }
+
+    /// Runs until a `Throw`/terminator opcode, a dispatch error, or `max_steps` instructions
+    /// have executed - whichever comes first.
+    pub fn run(&mut self, max_steps: usize) -> Result<(), VmError> {
+        for _ in 0..max_steps {
+            if self.step()? {
+                return Ok(());
+            }
+        }
+        Err(VmError::StepLimitExceeded)
+    }
+
+    /// Executes the opcode at the current `pc`, returning `Ok(true)` once the opcode table has
+    /// been exhausted with nothing left to fall through to.
+    fn step(&mut self) -> Result<bool, VmError> {
+        let opcode = self
+            .opcodes
+            .get(&self.pc)
+            .ok_or(VmError::UnknownOpcode(self.pc))?
+            .clone();
+
+        match &opcode {
+            Opcode::Binary(op) => {
+                let [dest, a, b]: [u16; 3] = match op.bits[..] {
+                    [dest, a, b] => [dest, a, b],
+                    _ => return Err(VmError::UnknownOpcode(self.pc)),
+                };
+                let (lhs, rhs) = if op.swap { (b, a) } else { (a, b) };
+                let result = self
+                    .register(lhs)
+                    .as_js()
+                    .zip(self.register(rhs).as_js())
+                    .and_then(|(l, r)| eval_binary_op_val(op.operator.get_operator(), &l, &r));
+                self.set_register(dest, result.map(Value::Scalar).unwrap_or(Value::Undefined));
+                return self.advance_fallthrough();
+            }
+            Opcode::Unary(op) => {
+                let [dest, src]: [u16; 2] = match op.bits[..] {
+                    [dest, src] => [dest, src],
+                    _ => return Err(VmError::UnknownOpcode(self.pc)),
+                };
+                let result = self
+                    .register(src)
+                    .as_js()
+                    .and_then(|v| eval_unary_op_val(op.operator.get_operator(), &v));
+                self.set_register(dest, result.map(Value::Scalar).unwrap_or(Value::Undefined));
+                return self.advance_fallthrough();
+            }
+            Opcode::NewLiteral(op) => {
+                let dest = *op.bits.first().ok_or(VmError::UnknownOpcode(self.pc))?;
+                // The obfuscator collapses every literal type this opcode can produce into one
+                // shared dispatch site; which one actually fires depends on a runtime selector
+                // this static pass never recovers. Taking the lowest test key is a deterministic
+                // stand-in, not a verified decode - see `RecursiveDisassembler::resolve_target`
+                // for the same kind of admitted gap.
+                let chosen = op.tests.iter().min_by_key(|(k, _)| **k).map(|(_, v)| v);
+                let value = match chosen.map(|t| &t.type_) {
+                    Some(LiteralType::Null) => Value::Null,
+                    Some(LiteralType::NaN) => Value::Scalar(JsValue::Num(f64::NAN)),
+                    Some(LiteralType::Infinity) => Value::Scalar(JsValue::Num(f64::INFINITY)),
+                    Some(LiteralType::True) => Value::Scalar(JsValue::Bool(true)),
+                    Some(LiteralType::False) => Value::Scalar(JsValue::Bool(false)),
+                    Some(LiteralType::Array) => Value::Array(Vec::new()),
+                    Some(LiteralType::Integer) | Some(LiteralType::String) => chosen
+                        .and_then(|t| t.bits.first())
+                        .and_then(|&idx| self.constants.get(idx as usize).cloned())
+                        .unwrap_or(Value::Undefined),
+                    _ => Value::Undefined,
+                };
+                self.set_register(dest, value);
+                return self.advance_fallthrough();
+            }
+            Opcode::NewObject(op) => {
+                let dest = *op.bits.first().ok_or(VmError::UnknownOpcode(self.pc))?;
+                self.set_register(dest, Value::Object(FxHashMap::default()));
+                return self.advance_fallthrough();
+            }
+            Opcode::NewArray(op) => {
+                let dest = *op.bits.first().ok_or(VmError::UnknownOpcode(self.pc))?;
+                self.set_register(dest, Value::Array(Vec::new()));
+                return self.advance_fallthrough();
+            }
+            Opcode::ArrayPush(op) => {
+                let [array, value]: [u16; 2] = match op.bits[..] {
+                    [array, value] => [array, value],
+                    _ => return Err(VmError::UnknownOpcode(self.pc)),
+                };
+                let pushed = self.register(value);
+                if let Value::Array(items) = &mut self.registers[array as usize] {
+                    items.push(pushed);
+                }
+                return self.advance_fallthrough();
+            }
+            Opcode::Pop(op) => {
+                let [array, dest]: [u16; 2] = match op.bits[..] {
+                    [array, dest] => [array, dest],
+                    _ => return Err(VmError::UnknownOpcode(self.pc)),
+                };
+                let popped = match self.registers.get_mut(array as usize) {
+                    Some(Value::Array(items)) => items.pop().unwrap_or(Value::Undefined),
+                    _ => Value::Undefined,
+                };
+                self.set_register(dest, popped);
+                return self.advance_fallthrough();
+            }
+            Opcode::SplicePop(op) => {
+                let [array, index, dest]: [u16; 3] = match op.bits[..] {
+                    [array, index, dest] => [array, index, dest],
+                    _ => return Err(VmError::UnknownOpcode(self.pc)),
+                };
+                let idx = self.register(index).as_js().map(|v| v.to_number());
+                let removed = match (self.registers.get_mut(array as usize), idx) {
+                    (Some(Value::Array(items)), Some(idx))
+                        if idx >= 0.0 && (idx as usize) < items.len() =>
+                    {
+                        items.remove(idx as usize)
+                    }
+                    _ => Value::Undefined,
+                };
+                self.set_register(dest, removed);
+                return self.advance_fallthrough();
+            }
+            Opcode::GetProperty(op) => {
+                let [dest, object, key]: [u16; 3] = match op.bits[..] {
+                    [dest, object, key] => [dest, object, key],
+                    _ => return Err(VmError::UnknownOpcode(self.pc)),
+                };
+                let key = self.register(key).object_key();
+                let value = match self.register(object) {
+                    Value::Object(map) => map.get(&key).cloned().unwrap_or(Value::Undefined),
+                    Value::Array(items) => key
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|i| items.get(i).cloned())
+                        .unwrap_or(Value::Undefined),
+                    _ => Value::Undefined,
+                };
+                self.set_register(dest, value);
+                return self.advance_fallthrough();
+            }
+            Opcode::SetProperty(op) => {
+                let [object, key, value]: [u16; 3] = match op.bits[..] {
+                    [object, key, value] => [object, key, value],
+                    _ => return Err(VmError::UnknownOpcode(self.pc)),
+                };
+                let key = self.register(key).object_key();
+                let value = self.register(value);
+                if let Value::Object(map) = &mut self.registers[object as usize] {
+                    map.insert(key, value);
+                }
+                return self.advance_fallthrough();
+            }
+            Opcode::Move(op) => {
+                let [dest, src]: [u16; 2] = match op.bits[..] {
+                    [dest, src] => [dest, src],
+                    _ => return Err(VmError::UnknownOpcode(self.pc)),
+                };
+                let value = self.register(src);
+                self.set_register(dest, value);
+                return self.advance_fallthrough();
+            }
+            Opcode::SwapRegister(op) => {
+                let [a, b]: [u16; 2] = match op.bits[..] {
+                    [a, b] => [a, b],
+                    _ => return Err(VmError::UnknownOpcode(self.pc)),
+                };
+                let max = a.max(b) as usize;
+                if max >= self.registers.len() {
+                    self.registers.resize(max + 1, Value::Undefined);
+                }
+                self.registers.swap(a as usize, b as usize);
+                return self.advance_fallthrough();
+            }
+            Opcode::Heap(op) => {
+                let slot = *op.bits.first().ok_or(VmError::UnknownOpcode(self.pc))?;
+                // Same collapsed-dispatch ambiguity as `NewLiteral` above - which of Set/Get/
+                // Init actually runs here isn't statically recovered, so the lowest test key
+                // is taken as the best-effort stand-in.
+                let chosen = op.closures.iter().min_by_key(|(k, _)| **k).map(|(_, v)| v);
+                match chosen.map(|c| &c.closure_type) {
+                    Some(HeapType::Get) => {
+                        let value = self.heap.get(&slot).cloned().unwrap_or(Value::Undefined);
+                        self.set_register(slot, value);
+                    }
+                    Some(HeapType::Set) | Some(HeapType::Init) => {
+                        let value = self.register(slot);
+                        self.heap.insert(slot, value);
+                    }
+                    None => {}
+                }
+                return self.advance_fallthrough();
+            }
+            Opcode::Jump(op) => {
+                let raw = *op.bits.first().ok_or(VmError::UnknownOpcode(self.pc))?;
+                self.pc = self.resolve_jump(raw)?;
+                Ok(false)
+            }
+            Opcode::JumpIf(op) => {
+                let [cond, if_true, if_false]: [u16; 3] = match op.bits[..] {
+                    [cond, if_true, if_false] => [cond, if_true, if_false],
+                    _ => return Err(VmError::UnknownOpcode(self.pc)),
+                };
+                let taken = self.register(cond).as_js().map(|v| v.to_bool()).unwrap_or(false);
+                let target_raw = if taken { if_true } else { if_false };
+                self.pc = self.resolve_jump(target_raw)?;
+                Ok(false)
+            }
+            Opcode::JumpIfTrue(op) => {
+                let taken = self.register(op.test).as_js().map(|v| v.to_bool()).unwrap_or(false);
+                if !taken {
+                    return self.advance_fallthrough();
+                }
+                let raw = *op.bits.first().ok_or(VmError::UnknownOpcode(self.pc))?;
+                self.pc = self.resolve_jump(raw)?;
+                Ok(false)
+            }
+            Opcode::JumpIfFalse(op) => {
+                let taken = self.register(op.test).as_js().map(|v| v.to_bool()).unwrap_or(false);
+                if taken {
+                    return self.advance_fallthrough();
+                }
+                let raw = *op.bits.first().ok_or(VmError::UnknownOpcode(self.pc))?;
+                self.pc = self.resolve_jump(raw)?;
+                Ok(false)
+            }
+            Opcode::Throw(op) => {
+                let reg = op.bits.first().copied().unwrap_or(0);
+                let message = match self.register(reg) {
+                    Value::Scalar(v) => v.to_number().to_string(),
+                    other => format!("{other:?}"),
+                };
+                Err(VmError::Thrown(message))
+            }
+            Opcode::LoadReceiver(op) => {
+                // The VM's `this`/global receiver isn't modeled any more than a host call's
+                // return value is (see the arm below) - there's no sandboxed `window`/`globalThis`
+                // for it to resolve to, so the destination is left undefined rather than guessed.
+                let dest = *op.bits.first().ok_or(VmError::UnknownOpcode(self.pc))?;
+                self.set_register(dest, Value::Undefined);
+                return self.advance_fallthrough();
+            }
+            // Host calls aren't modeled - there's no sandboxed runtime for them to actually
+            // invoke, so this just consumes the opcode without touching registers beyond
+            // leaving the destination (if any) undefined, so the fallthrough keeps going
+            // instead of hanging.
+            Opcode::Bind(_)
+            | Opcode::RegisterVMFunction(_)
+            | Opcode::Call(_)
+            | Opcode::CallFuncNoContext(_) => self.advance_fallthrough(),
+        }
+    }
+
+    /// Advances `pc` to the next opcode in key order (straight-line fallthrough), returning
+    /// `Ok(true)` once there's nothing left to fall through to - the table's been exhausted,
+    /// which is this VM's only notion of "the program finished" since none of the recovered
+    /// opcodes is an explicit halt.
+    fn advance_fallthrough(&mut self) -> Result<bool, VmError> {
+        match self.fallthrough() {
+            Some(next) => {
+                self.pc = next;
+                Ok(false)
+            }
+            None => Ok(true),
+        }
+    }
+}
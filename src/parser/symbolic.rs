@@ -0,0 +1,320 @@
+use crate::parser::functions::{eval_binary_op_val, eval_unary_op_val, JsValue};
+use crate::parser::magic_bits::{BinaryOperator, LiteralType, Opcode, UnaryOperator};
+use rustc_hash::FxHashMap;
+use std::rc::Rc;
+
+/// A register's value as an expression tree instead of a concrete `interpreter::Value` - the
+/// point of this pass isn't to run the script, it's to see what `OpcodeParser`'s
+/// `handle_binary_opcodes`/`handle_unary_opcodes` actually wired together (operator, operand
+/// order, `swap` flag) without having to supply real inputs.
+///
+/// Deliberately narrower than `interpreter::Value`: no `Array`/`Object`/heap variants, since
+/// anything this pass can't express symbolically (host calls, heap reads, collection mutation)
+/// just collapses to `Unknown` rather than being modeled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Const(JsValue),
+    Reg(u16),
+    Unary(UnaryOperator, Box<Expr>),
+    Binary(BinaryOperator, Box<Expr>, Box<Expr>),
+    Property(Box<Expr>, Box<Expr>),
+    /// Anything this pass doesn't track symbolically: heap get/set, object/array literals,
+    /// `Call`/`CallFuncNoContext`/`Bind` results.
+    Unknown,
+}
+
+/// Folds what `mk_binary`/`mk_unary` can prove at construction time: full evaluation when both
+/// operands are already `Const` (reusing `eval_binary_op_val`'s JS coercion rules, same as
+/// `interpreter::OpcodeVM`), plus a handful of algebraic identities that hold regardless of what
+/// the non-constant operand turns out to be.
+fn mk_binary(op: BinaryOperator, lhs: Expr, rhs: Expr) -> Expr {
+    if let (Expr::Const(l), Expr::Const(r)) = (&lhs, &rhs) {
+        if let Some(folded) = eval_binary_op_val(op.get_operator(), l, r) {
+            return Expr::Const(folded);
+        }
+        // Both sides constant but `eval_binary_op_val` couldn't fold (e.g. an operator it
+        // doesn't know): `===`/`==` on two constants that didn't already fold above means they
+        // compared unequal by type/value, so the comparison itself is foldable to `false`.
+        if matches!(op, BinaryOperator::EqualsStrict) {
+            return Expr::Const(JsValue::Bool(false));
+        }
+    }
+
+    match (&op, &lhs, &rhs) {
+        (BinaryOperator::Addition, _, Expr::Const(JsValue::Num(n))) if *n == 0.0 => return lhs,
+        (BinaryOperator::Addition, Expr::Const(JsValue::Num(n)), _) if *n == 0.0 => return rhs,
+        (BinaryOperator::Multiplication, _, Expr::Const(JsValue::Num(n))) if *n == 1.0 => {
+            return lhs
+        }
+        (BinaryOperator::Multiplication, Expr::Const(JsValue::Num(n)), _) if *n == 1.0 => {
+            return rhs
+        }
+        (BinaryOperator::Multiplication, _, Expr::Const(JsValue::Num(n))) if *n == 0.0 => {
+            return Expr::Const(JsValue::Num(0.0))
+        }
+        (BinaryOperator::Multiplication, Expr::Const(JsValue::Num(n)), _) if *n == 0.0 => {
+            return Expr::Const(JsValue::Num(0.0))
+        }
+        _ => {}
+    }
+
+    Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+}
+
+fn mk_unary(op: UnaryOperator, operand: Expr) -> Expr {
+    if let Expr::Const(v) = &operand {
+        if let Some(folded) = eval_unary_op_val(op.get_operator(), v) {
+            return Expr::Const(folded);
+        }
+    }
+
+    // `!!x` is the idiomatic boolean-cast obfuscators lean on; treating it as an identity is
+    // imprecise (it discards the coercion to `bool`) but this pass only cares about recovering
+    // the *shape* of an expression for the decompiler/sanity-check, not a faithful reduction.
+    if op == UnaryOperator::LogicalNot {
+        if let Expr::Unary(UnaryOperator::LogicalNot, inner) = operand {
+            return *inner;
+        }
+    }
+
+    Expr::Unary(op, Box::new(operand))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolicError {
+    UnknownOpcode(u16),
+    UnresolvedJumpTarget(u16),
+    StepLimitExceeded,
+}
+
+impl std::fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymbolicError::UnknownOpcode(pc) => write!(f, "no recovered opcode at register {pc}"),
+            SymbolicError::UnresolvedJumpTarget(raw) => {
+                write!(f, "jump operand {raw} did not resolve to a known opcode")
+            }
+            SymbolicError::StepLimitExceeded => write!(f, "exceeded step budget without halting"),
+        }
+    }
+}
+
+impl std::error::Error for SymbolicError {}
+
+/// Walks the recovered opcode table the same way `interpreter::OpcodeVM` does, but with
+/// registers holding `Expr` trees instead of concrete `Value`s, so the disassembler can check
+/// `handle_binary_opcodes`' `operator`/`swap` assignment produced the expression a human reading
+/// the obfuscated source would expect, before `decompiler` renders it as JS.
+pub struct SymbolicVM<'a> {
+    opcodes: &'a FxHashMap<u16, Opcode>,
+    keys: Vec<u16>,
+    key: u16,
+    constants: Vec<Expr>,
+    registers: Vec<Rc<Expr>>,
+    pc: u16,
+}
+
+impl<'a> SymbolicVM<'a> {
+    pub fn new(opcodes: &'a FxHashMap<u16, Opcode>, constants: Vec<Expr>, key: u16, entry: u16) -> Self {
+        let mut keys: Vec<u16> = opcodes.keys().copied().collect();
+        keys.sort_unstable();
+
+        Self {
+            opcodes,
+            keys,
+            key,
+            constants,
+            registers: Vec::new(),
+            pc: entry,
+        }
+    }
+
+    pub fn register(&self, idx: u16) -> Rc<Expr> {
+        self.registers
+            .get(idx as usize)
+            .cloned()
+            .unwrap_or_else(|| Rc::new(Expr::Reg(idx)))
+    }
+
+    fn set_register(&mut self, idx: u16, value: Rc<Expr>) {
+        let idx = idx as usize;
+        if idx >= self.registers.len() {
+            self.registers.resize(idx + 1, Rc::new(Expr::Unknown));
+        }
+        self.registers[idx] = value;
+    }
+
+    /// Same XOR-against-key decode `interpreter::OpcodeVM::resolve_jump` uses.
+    fn resolve_jump(&self, raw: u16) -> Result<u16, SymbolicError> {
+        let target = raw ^ self.key;
+        if self.opcodes.contains_key(&target) {
+            Ok(target)
+        } else {
+            Err(SymbolicError::UnresolvedJumpTarget(raw))
+        }
+    }
+
+    fn fallthrough(&self) -> Option<u16> {
+        self.keys.iter().copied().find(|&k| k > self.pc)
+    }
+
+    /// Runs until the opcode table is exhausted, a dispatch error, or `max_steps` instructions -
+    /// same completion convention as `interpreter::OpcodeVM::run`.
+    pub fn run(&mut self, max_steps: usize) -> Result<(), SymbolicError> {
+        for _ in 0..max_steps {
+            if self.step()? {
+                return Ok(());
+            }
+        }
+        Err(SymbolicError::StepLimitExceeded)
+    }
+
+    fn step(&mut self) -> Result<bool, SymbolicError> {
+        let opcode = self
+            .opcodes
+            .get(&self.pc)
+            .ok_or(SymbolicError::UnknownOpcode(self.pc))?
+            .clone();
+
+        match &opcode {
+            Opcode::Binary(op) => {
+                let [dest, a, b]: [u16; 3] = match op.bits[..] {
+                    [dest, a, b] => [dest, a, b],
+                    _ => return Err(SymbolicError::UnknownOpcode(self.pc)),
+                };
+                let (lhs, rhs) = if op.swap { (b, a) } else { (a, b) };
+                let expr = mk_binary(
+                    op.operator.clone(),
+                    (*self.register(lhs)).clone(),
+                    (*self.register(rhs)).clone(),
+                );
+                self.set_register(dest, Rc::new(expr));
+                return self.advance_fallthrough();
+            }
+            Opcode::Unary(op) => {
+                let [dest, src]: [u16; 2] = match op.bits[..] {
+                    [dest, src] => [dest, src],
+                    _ => return Err(SymbolicError::UnknownOpcode(self.pc)),
+                };
+                let expr = mk_unary(op.operator.clone(), (*self.register(src)).clone());
+                self.set_register(dest, Rc::new(expr));
+                return self.advance_fallthrough();
+            }
+            Opcode::NewLiteral(op) => {
+                let dest = *op.bits.first().ok_or(SymbolicError::UnknownOpcode(self.pc))?;
+                // Same collapsed-dispatch ambiguity `interpreter::OpcodeVM` documents for this
+                // opcode - the lowest test key stands in for the runtime selector.
+                let chosen = op.tests.iter().min_by_key(|(k, _)| **k).map(|(_, v)| v);
+                let expr = match chosen.map(|t| &t.type_) {
+                    // `Expr` has no `Null` variant (unlike `interpreter::Value::Null`) - falling
+                    // back to `Unknown` keeps `null` from constant-folding into an equality it
+                    // doesn't actually hold (e.g. `null === false` is `false` in JS).
+                    Some(LiteralType::Null) => Expr::Unknown,
+                    Some(LiteralType::NaN) => Expr::Const(JsValue::Num(f64::NAN)),
+                    Some(LiteralType::Infinity) => Expr::Const(JsValue::Num(f64::INFINITY)),
+                    Some(LiteralType::True) => Expr::Const(JsValue::Bool(true)),
+                    Some(LiteralType::False) => Expr::Const(JsValue::Bool(false)),
+                    Some(LiteralType::Integer) | Some(LiteralType::String) => chosen
+                        .and_then(|t| t.bits.first())
+                        .and_then(|&idx| self.constants.get(idx as usize).cloned())
+                        .unwrap_or(Expr::Unknown),
+                    _ => Expr::Unknown,
+                };
+                self.set_register(dest, Rc::new(expr));
+                return self.advance_fallthrough();
+            }
+            Opcode::GetProperty(op) => {
+                let [dest, object, key]: [u16; 3] = match op.bits[..] {
+                    [dest, object, key] => [dest, object, key],
+                    _ => return Err(SymbolicError::UnknownOpcode(self.pc)),
+                };
+                let expr = Expr::Property(
+                    Box::new((*self.register(object)).clone()),
+                    Box::new((*self.register(key)).clone()),
+                );
+                self.set_register(dest, Rc::new(expr));
+                return self.advance_fallthrough();
+            }
+            Opcode::Move(op) => {
+                let [dest, src]: [u16; 2] = match op.bits[..] {
+                    [dest, src] => [dest, src],
+                    _ => return Err(SymbolicError::UnknownOpcode(self.pc)),
+                };
+                let value = self.register(src);
+                self.set_register(dest, value);
+                return self.advance_fallthrough();
+            }
+            Opcode::SwapRegister(op) => {
+                let [a, b]: [u16; 2] = match op.bits[..] {
+                    [a, b] => [a, b],
+                    _ => return Err(SymbolicError::UnknownOpcode(self.pc)),
+                };
+                let (va, vb) = (self.register(a), self.register(b));
+                self.set_register(a, vb);
+                self.set_register(b, va);
+                return self.advance_fallthrough();
+            }
+            Opcode::Jump(op) => {
+                let raw = *op.bits.first().ok_or(SymbolicError::UnknownOpcode(self.pc))?;
+                self.pc = self.resolve_jump(raw)?;
+                Ok(false)
+            }
+            Opcode::JumpIf(op) => {
+                let [cond, if_true, if_false]: [u16; 3] = match op.bits[..] {
+                    [cond, if_true, if_false] => [cond, if_true, if_false],
+                    _ => return Err(SymbolicError::UnknownOpcode(self.pc)),
+                };
+                // No concrete value to branch on symbolically - both arms would need exploring
+                // to be exhaustive, but this pass only exists to sanity-check operator/operand
+                // wiring, so taking the "true" edge is a deterministic, good-enough choice
+                // rather than forking the walk.
+                let _ = cond;
+                let target = self.resolve_jump(if_true).or_else(|_| self.resolve_jump(if_false))?;
+                self.pc = target;
+                Ok(false)
+            }
+            // Same "take the available edge" stand-in as `JumpIf` above, specialized to the
+            // directional jumps: `JumpIfTrue`'s only recovered target is its taken edge, and the
+            // untaken edge is just the ordinary fallthrough (and the mirror for `JumpIfFalse`).
+            Opcode::JumpIfTrue(op) => {
+                let raw = *op.bits.first().ok_or(SymbolicError::UnknownOpcode(self.pc))?;
+                self.pc = self.resolve_jump(raw)?;
+                Ok(false)
+            }
+            Opcode::JumpIfFalse(op) => {
+                let raw = *op.bits.first().ok_or(SymbolicError::UnknownOpcode(self.pc))?;
+                self.pc = self.resolve_jump(raw)?;
+                Ok(false)
+            }
+            // Everything else either writes a register with something this pass can't express
+            // symbolically (`NewObject`/`NewArray`/`ArrayPush`/`Pop`/`SplicePop`/`SetProperty`/
+            // `Heap`/host calls) or doesn't write one at all (`Throw`) - either way execution
+            // just keeps falling through with the destination (if any) marked `Unknown`.
+            _ => {
+                if let Some(dest) = destination_register(&opcode) {
+                    self.set_register(dest, Rc::new(Expr::Unknown));
+                }
+                self.advance_fallthrough()
+            }
+        }
+    }
+
+    fn advance_fallthrough(&mut self) -> Result<bool, SymbolicError> {
+        match self.fallthrough() {
+            Some(next) => {
+                self.pc = next;
+                Ok(false)
+            }
+            None => Ok(true),
+        }
+    }
+}
+
+/// Mirrors `RecursiveDisassembler::def_use`'s convention for which bit is the destination slot,
+/// for the opcodes `SymbolicVM::step`'s fallback arm handles generically.
+fn destination_register(opcode: &Opcode) -> Option<u16> {
+    if opcode.is_branch() || opcode.is_terminator() {
+        return None;
+    }
+    opcode.bits().first().copied()
+}
@@ -1,15 +1,112 @@
 use oxc_ast::ast::{CallExpression, Expression, StringLiteral};
 use oxc_ast_visit::walk::walk_call_expression;
 use oxc_ast_visit::Visit;
+use rustc_hash::FxHashMap;
+
+/// One string-literal call argument considered for a payload role (`initial_vm`/`main_vm`),
+/// along with the plausibility score `score_candidate` gave it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub value: String,
+    pub score: f64,
+}
 
 #[derive(Default, Debug)]
 pub struct ScriptVisitor {
-    pub initial_vm: Option<String>,
-    pub main_vm: Option<String>,
+    /// Candidate initial-VM payloads, sorted by `score` descending (highest-scoring first).
+    pub initial_vm_candidates: Vec<Candidate>,
+    /// Candidate main-VM payloads, sorted by `score` descending (highest-scoring first).
+    pub main_vm_candidates: Vec<Candidate>,
     pub compressor_charset: Option<String>,
     pub init_argument: Option<String>,
 }
 
+impl ScriptVisitor {
+    /// The best-scoring initial-VM candidate found, if any.
+    pub fn initial_vm(&self) -> Option<&Candidate> {
+        self.initial_vm_candidates.first()
+    }
+
+    /// The best-scoring main-VM candidate found, if any.
+    pub fn main_vm(&self) -> Option<&Candidate> {
+        self.main_vm_candidates.first()
+    }
+}
+
+/// The 65-char `ScriptVisitor::compressor_charset` alphabet doubles as a reasonable
+/// "looks bit-packed" signal even for a string that hasn't been matched to that exact charset:
+/// base64's own alphabet is a subset of printable ASCII that obfuscated payloads conform to
+/// almost entirely, so conformance against it is cheap to check without depending on having
+/// already recovered the real charset elsewhere in the walk.
+const BASE64_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+
+fn shannon_entropy(value: &str) -> f64 {
+    if value.is_empty() {
+        return 0.0;
+    }
+    let mut counts: FxHashMap<u8, usize> = FxHashMap::default();
+    for byte in value.bytes() {
+        *counts.entry(byte).or_insert(0) += 1;
+    }
+    let len = value.len() as f64;
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+fn alphabet_conformance(value: &str) -> f64 {
+    if value.is_empty() {
+        return 0.0;
+    }
+    let conforming = value.chars().filter(|c| BASE64_ALPHABET.contains(*c)).count();
+    conforming as f64 / value.chars().count() as f64
+}
+
+/// Scores a string-literal call argument's plausibility as a VM payload, replacing the old
+/// `len > 300` / `len >= 1000` cutoffs with several weighted signals so a borderline candidate
+/// isn't silently mis-bucketed or dropped:
+/// - length: longer payloads score higher, saturating past 1000 chars so one huge string can't
+///   dominate the ranking on size alone
+/// - entropy: Shannon entropy over the byte distribution - bit-packed bytecode reads closer to
+///   random noise than prose or identifiers
+/// - alphabet conformance: fraction of characters drawn from the base64/lz-string alphabet,
+///   since payloads are bit-packed through one of those
+/// - non-ASCII ratio: counts against the score, since a meaningfully non-ASCII string is far more
+///   likely to be ordinary string content than bytecode
+/// - `has_key_arg`: obfuscated VM payload calls commonly pass a second "key" argument alongside
+///   the payload string, so its presence nudges the score up
+fn score_candidate(value: &str, has_key_arg: bool) -> f64 {
+    let length_score = (value.len() as f64 / 1000.0).min(1.0);
+    let entropy_score = (shannon_entropy(value) / 8.0).min(1.0);
+    let alphabet_score = alphabet_conformance(value);
+
+    let non_ascii = value.chars().filter(|c| !c.is_ascii()).count() as f64;
+    let non_ascii_ratio = if value.is_empty() {
+        0.0
+    } else {
+        non_ascii / value.chars().count() as f64
+    };
+
+    let key_bonus = if has_key_arg { 0.15 } else { 0.0 };
+
+    0.35 * length_score + 0.25 * entropy_score + 0.25 * alphabet_score - 0.2 * non_ascii_ratio
+        + key_bonus
+}
+
+/// Inserts `candidate` into `candidates`, keeping the list sorted by score descending so the
+/// best-scoring candidate for a role is always `candidates.first()`.
+fn insert_ranked(candidates: &mut Vec<Candidate>, candidate: Candidate) {
+    let pos = candidates
+        .iter()
+        .position(|existing| existing.score < candidate.score)
+        .unwrap_or(candidates.len());
+    candidates.insert(pos, candidate);
+}
+
 impl<'a> Visit<'a> for ScriptVisitor {
     fn visit_call_expression(&mut self, it: &CallExpression<'a>) {
         // On vérifie que c'est bien un appel de fonction standard
@@ -37,21 +134,21 @@ impl<'a> Visit<'a> for ScriptVisitor {
             _ => return, // Safety: on ignore si ce n'est pas une string simple
         };
 
-        // HEURISTIQUE DE TAILLE :
+        // HEURISTIQUE DE TAILLE pour le rôle (bucket large, le score départage le reste) :
         // Le bytecode initial fait généralement entre 300 et 800 caractères.
         // Le main bytecode fait plus de 1000 caractères.
-        
         let len = first_arg_str.len();
+        let has_key_arg = it.arguments.len() > 1;
 
         if len > 300 {
+            let candidate = Candidate {
+                value: first_arg_str.to_string(),
+                score: score_candidate(first_arg_str, has_key_arg),
+            };
             if len >= 1000 {
-                // C'est probablement le Main VM Payload
-                self.main_vm = Some(first_arg_str.to_string());
-            } else if self.initial_vm.is_none() {
-                // C'est probablement l'Initial VM Payload (anciennement atob)
-                // On prend le premier candidat valide qu'on trouve.
-                // On ne vérifie PLUS le nom de la fonction (callee) car il change souvent.
-                self.initial_vm = Some(first_arg_str.to_string());
+                insert_ranked(&mut self.main_vm_candidates, candidate);
+            } else {
+                insert_ranked(&mut self.initial_vm_candidates, candidate);
             }
         }
 
@@ -74,4 +171,126 @@ impl<'a> Visit<'a> for ScriptVisitor {
             self.init_argument = Some(it.value.to_string());
         }
     }
+}
+
+/// Reads bits MSB-first out of a stream of characters, each contributing `log2(charset.len())`
+/// bits via the reverse charset map - the bit order lz-string's own decompressor reads in: each
+/// new bit is read from the current character's highest remaining unread bit, then folded into
+/// the output value as the *next least-significant* bit, advancing to the next character once the
+/// current one is exhausted.
+struct LzBitReader<'a> {
+    chars: std::str::Chars<'a>,
+    charset: &'a FxHashMap<char, u32>,
+    val: u32,
+    position: u32,
+}
+
+impl<'a> LzBitReader<'a> {
+    fn new(payload: &'a str, charset: &'a FxHashMap<char, u32>) -> Option<Self> {
+        let mut chars = payload.chars();
+        let val = *charset.get(&chars.next()?)?;
+        Some(Self {
+            chars,
+            charset,
+            val,
+            position: 32,
+        })
+    }
+
+    fn read_bit(&mut self) -> Option<u32> {
+        let bit = u32::from((self.val & self.position) != 0);
+        self.position >>= 1;
+        if self.position == 0 {
+            self.position = 32;
+            self.val = *self.charset.get(&self.chars.next()?)?;
+        }
+        Some(bit)
+    }
+
+    fn read_bits(&mut self, count: u32) -> Option<u32> {
+        let mut bits = 0u32;
+        let mut power = 1u32;
+        for _ in 0..count {
+            bits |= self.read_bit()? * power;
+            power <<= 1;
+        }
+        Some(bits)
+    }
+}
+
+/// Decodes an lz-string bit-packed payload (the format `ScriptVisitor` recovers `initial_vm`/
+/// `main_vm` as) using the recovered 65-character `compressor_charset` as the 6-bit-index
+/// alphabet. Mirrors lz-string's own `_decompress`: a 2-bit header selects how the first
+/// dictionary entry (and `result`/`w`) is seeded - an 8-bit codepoint, a 16-bit codepoint, or the
+/// empty string - then each iteration reads a `num_bits`-wide token that either decodes another
+/// literal codepoint into a fresh dictionary slot, closes the stream, or indexes an existing
+/// entry (falling back to `w + first_char(w)` for the not-yet-materialized self-reference case).
+/// `num_bits` grows as the dictionary does, doubling `enlarge_in`'s budget each time it's spent.
+/// Returns `None` as soon as the bitstream runs out or a decoded index falls outside the
+/// dictionary, rather than guessing - a malformed payload should fail rather than desync silently.
+pub fn decompress(payload: &str, charset: &str) -> Option<String> {
+    let reverse: FxHashMap<char, u32> = charset.chars().enumerate().map(|(i, c)| (c, i as u32)).collect();
+    let mut reader = LzBitReader::new(payload, &reverse)?;
+
+    let mut dictionary: Vec<String> = vec![String::new(), String::new(), String::new()];
+    let mut num_bits = 3u32;
+    let mut enlarge_in = 4i64;
+
+    let first = match reader.read_bits(2)? {
+        0 => char::from_u32(reader.read_bits(8)?)?,
+        1 => char::from_u32(reader.read_bits(16)?)?,
+        2 => return Some(String::new()),
+        _ => return None,
+    };
+
+    dictionary.push(first.to_string());
+    let mut w = first.to_string();
+    let mut result = String::new();
+    result.push(first);
+
+    loop {
+        let token = reader.read_bits(num_bits)?;
+        let mut c = token as usize;
+
+        match token {
+            0 => {
+                let ch = char::from_u32(reader.read_bits(8)?)?;
+                dictionary.push(ch.to_string());
+                c = dictionary.len() - 1;
+                enlarge_in -= 1;
+            }
+            1 => {
+                let ch = char::from_u32(reader.read_bits(16)?)?;
+                dictionary.push(ch.to_string());
+                c = dictionary.len() - 1;
+                enlarge_in -= 1;
+            }
+            2 => return Some(result),
+            _ => {}
+        }
+
+        if enlarge_in == 0 {
+            enlarge_in = 1i64 << num_bits;
+            num_bits += 1;
+        }
+
+        let entry = if c < dictionary.len() && !dictionary[c].is_empty() {
+            dictionary[c].clone()
+        } else if c == dictionary.len() {
+            format!("{w}{}", w.chars().next()?)
+        } else {
+            return None;
+        };
+
+        result.push_str(&entry);
+
+        dictionary.push(format!("{w}{}", entry.chars().next()?));
+        enlarge_in -= 1;
+        w = entry;
+
+        if enlarge_in == 0 {
+            enlarge_in = 1i64 << num_bits;
+            num_bits += 1;
+        }
+    }
 }
\ No newline at end of file
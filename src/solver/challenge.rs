@@ -1,10 +1,127 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use oxc_allocator::Allocator;
-use oxc_ast::ast::{Expression, ObjectPropertyKind};
+use oxc_ast::ast::{ArrayExpressionElement, BindingPatternKind, Expression, ObjectPropertyKind, Statement};
 use oxc_ast_visit::{Visit, walk::walk_object_expression};
 use oxc_parser::Parser;
 use oxc_span::SourceType;
 use regex::Regex;
+use serde::Deserialize;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::collections::HashMap;
+use std::io::Read as _;
+
+/// Leading magic bytes for each compression format `decode_challenge_body` understands, in the
+/// order they're tried. Brotli has no magic number, so it isn't in this table - it's only
+/// attempted after every signature here fails to match (see `decode_challenge_body`).
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ZLIB_MAGIC_PREFIXES: [u8; 3] = [0x01, 0x9C, 0xDA];
+
+/// Decodes a challenge body that may or may not still be compressed, sniffing a leading magic
+/// signature rather than trusting a declared `Content-Encoding` - callers of `from_bytes` often
+/// don't have one. Tries, in order: gzip (`1F 8B`), zlib/deflate (`78` followed by one of the
+/// standard `01`/`9C`/`DA` flag bytes), then brotli (no magic number, so it's a last-resort
+/// attempt rather than a sniffed match), finally falling back to treating `bytes` as plain UTF-8.
+fn decode_challenge_body(bytes: &[u8]) -> Result<String> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decoder = flate2::read::GzDecoder::new(bytes);
+        let mut decoded = String::new();
+        decoder
+            .read_to_string(&mut decoded)
+            .context("gzip-sniffed challenge body did not decode to valid UTF-8")?;
+        return Ok(decoded);
+    }
+
+    if bytes.len() >= 2 && bytes[0] == 0x78 && ZLIB_MAGIC_PREFIXES.contains(&bytes[1]) {
+        let mut decoder = flate2::read::ZlibDecoder::new(bytes);
+        let mut decoded = String::new();
+        decoder
+            .read_to_string(&mut decoded)
+            .context("zlib-sniffed challenge body did not decode to valid UTF-8")?;
+        return Ok(decoded);
+    }
+
+    let mut brotli_attempt = String::new();
+    if brotli::Decompressor::new(bytes, 4096)
+        .read_to_string(&mut brotli_attempt)
+        .is_ok()
+    {
+        return Ok(brotli_attempt);
+    }
+
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| anyhow!("challenge body is neither a recognized compressed format nor valid UTF-8"))
+}
+
+/// Folds constant JS the way a minifier would, so `CloudflareChallengeOptionsVisitor` can read
+/// property values Cloudflare writes as a concatenation, template literal, or array-index
+/// lookup instead of a bare string literal - all common once `_cf_chl_opt` is obfuscated.
+/// `arrays` is the table of top-level `var NAME = ["s0", "s1", ...]` declarations collected by
+/// `collect_string_arrays`, used to resolve `NAME[<numericliteral>]` lookups. Returns `None` on
+/// anything it can't fully resolve (an unknown identifier, a non-literal index, ...) rather
+/// than a partial string, since a wrong field value is worse than a missing one.
+fn eval_const(expr: &Expression, arrays: &HashMap<String, Vec<String>>) -> Option<String> {
+    match expr {
+        Expression::StringLiteral(lit) => Some(lit.value.to_string()),
+        Expression::NumericLiteral(lit) => Some(lit.value.to_string()),
+        Expression::ParenthesizedExpression(paren) => eval_const(&paren.expression, arrays),
+        Expression::BinaryExpression(bin) if bin.operator.as_str() == "+" => {
+            let left = eval_const(&bin.left, arrays)?;
+            let right = eval_const(&bin.right, arrays)?;
+            Some(left + &right)
+        }
+        Expression::TemplateLiteral(tpl) if tpl.expressions.is_empty() => {
+            let mut out = String::new();
+            for quasi in &tpl.quasis {
+                out.push_str(quasi.value.cooked.as_ref()?.as_str());
+            }
+            Some(out)
+        }
+        Expression::ComputedMemberExpression(member) => {
+            let Expression::Identifier(ident) = &member.object else {
+                return None;
+            };
+            let table = arrays.get(ident.name.as_str())?;
+            let Expression::NumericLiteral(idx) = &member.expression else {
+                return None;
+            };
+            table.get(idx.value as usize).cloned()
+        }
+        _ => None,
+    }
+}
+
+/// Pre-pass over the script's top-level statements, recording every `var`/`const NAME =
+/// ["s0", "s1", ...]` array-of-string-literals declaration so `eval_const` can resolve
+/// `NAME[<n>]` lookups against it. Takes the first binding if a name is declared twice.
+fn collect_string_arrays(body: &[Statement]) -> HashMap<String, Vec<String>> {
+    let mut arrays = HashMap::new();
+    for stmt in body {
+        let Statement::VariableDeclaration(decl) = stmt else {
+            continue;
+        };
+        for declarator in &decl.declarations {
+            let BindingPatternKind::BindingIdentifier(ident) = &declarator.id.kind else {
+                continue;
+            };
+            let Some(Expression::ArrayExpression(array)) = &declarator.init else {
+                continue;
+            };
+            let elements: Option<Vec<String>> = array
+                .elements
+                .iter()
+                .map(|el| match el {
+                    ArrayExpressionElement::StringLiteral(lit) => Some(lit.value.to_string()),
+                    _ => None,
+                })
+                .collect();
+            if let Some(elements) = elements {
+                arrays.entry(ident.name.to_string()).or_insert(elements);
+            }
+        }
+    }
+    arrays
+}
 
 #[derive(Debug, Default, Clone)]
 pub struct CloudflareChallengeOptions {
@@ -26,12 +143,61 @@ pub struct CloudflareChallengeOptions {
     pub iss_ua: String,
     pub ip: String,
     pub turnstile_u: String,
+    /// The `integrity` attribute on the `<script>` element that defines `_cf_chl_opt`, if any -
+    /// one or more space-separated `<alg>-<base64digest>` candidates per the SRI spec. Populated
+    /// by `parse_with_ast`/`parse_with_regex`; checked against the script body by
+    /// `verify_integrity`.
+    pub integrity: Option<String>,
 }
 
 struct CloudflareChallengeOptionsVisitor {
-    candidates: Vec<String>,
+    /// `(property key, resolved value)` pairs, preserving declaration order.
+    candidates: Vec<(String, String)>,
     options: CloudflareChallengeOptions,
     found_target: bool,
+    arrays: HashMap<String, Vec<String>>,
+}
+
+/// Reads an object property's key as a plain string, for both `{ cType: ... }` (identifier
+/// key) and `{ "cType": ... }` (string-literal key) forms - Cloudflare's obfuscator emits
+/// either depending on minifier pass. Computed keys (`{ [expr]: ... }`) aren't resolvable here
+/// and return `None`.
+fn property_key_name(key: &oxc_ast::ast::PropertyKey) -> Option<String> {
+    match key {
+        oxc_ast::ast::PropertyKey::StaticIdentifier(ident) => Some(ident.name.to_string()),
+        oxc_ast::ast::PropertyKey::StringLiteral(lit) => Some(lit.value.to_string()),
+        _ => None,
+    }
+}
+
+/// Canonical `_cf_chl_opt` property key -> `CloudflareChallengeOptions` field, so fields are
+/// assigned by what Cloudflare actually calls them instead of guessed from the value's shape.
+/// This is what fills `zone`/`widget_id`/`site_key`/`api_mode`/`api_size`/... which the
+/// length/prefix heuristics below never populate on their own.
+const KEY_FIELD_MAP: &[(&str, fn(&mut CloudflareChallengeOptions, String))] = &[
+    ("cType", |o, v| o.c_type = v),
+    ("cvId", |o, v| o.cv_id = v),
+    ("cArg", |o, v| o.c_arg = v),
+    ("cZone", |o, v| o.zone = v),
+    ("chlApiVId", |o, v| o.api_v_id = v),
+    ("chlApiWidgetId", |o, v| o.widget_id = v),
+    ("chlApiSitekey", |o, v| o.site_key = v),
+    ("chlApiMode", |o, v| o.api_mode = v),
+    ("chlApiSize", |o, v| o.api_size = v),
+    ("chlApiRcv", |o, v| o.api_rcv = v),
+    ("chlApiResetSrc", |o, v| o.reset_src = v),
+    ("cRay", |o, v| o.c_ray = v),
+    ("cH", |o, v| o.ch = v),
+    ("md", |o, v| o.md = v),
+    ("chlApiTimeoutEncountered", |o, v| o.time = v),
+];
+
+fn apply_key_based_mapping(opts: &mut CloudflareChallengeOptions, candidates: &[(String, String)]) {
+    for (key, value) in candidates {
+        if let Some((_, setter)) = KEY_FIELD_MAP.iter().find(|(k, _)| *k == key) {
+            setter(opts, value.clone());
+        }
+    }
 }
 
 impl<'a> Visit<'a> for CloudflareChallengeOptionsVisitor {
@@ -52,11 +218,14 @@ impl<'a> Visit<'a> for CloudflareChallengeOptionsVisitor {
 
         if is_target {
             self.found_target = true;
-            // Phase 2: Collect all string values preserving order
+            // Phase 2: Collect every resolvable value preserving order, folding constant
+            // concatenations/template literals/array lookups the same way a minifier would
+            // instead of only collecting bare string literals.
             for prop in &expr.properties {
                 if let ObjectPropertyKind::ObjectProperty(p) = prop {
-                    if let Expression::StringLiteral(val) = &p.value {
-                        self.candidates.push(val.value.as_str().to_string());
+                    if let Some(value) = eval_const(&p.value, &self.arrays) {
+                        let key = property_key_name(&p.key).unwrap_or_default();
+                        self.candidates.push((key, value));
                     }
                 }
             }
@@ -66,6 +235,76 @@ impl<'a> Visit<'a> for CloudflareChallengeOptionsVisitor {
     }
 }
 
+/// Distinguishes a recognized-but-unsupported SRI algorithm from the generic failures
+/// `anyhow::Error` covers everywhere else in this module, the same reasoning as
+/// `task_client::Error::PolicyBlocked`.
+#[derive(Debug)]
+pub enum Error {
+    UnsupportedIntegrityAlgorithm(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnsupportedIntegrityAlgorithm(alg) => {
+                write!(f, "unsupported subresource integrity algorithm '{alg}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Finds the `<script>` element that defines `_cf_chl_opt`, returning `(tag_start,
+/// content_start, content_end)` byte offsets into `html` - the same slicing `parse_with_ast` and
+/// `verify_integrity` both need, kept in one place so they can't drift apart.
+fn locate_challenge_script(html: &str) -> Result<(usize, usize, usize)> {
+    let keyword = "_cf_chl_opt";
+    let key_idx = html
+        .find(keyword)
+        .ok_or_else(|| anyhow!("Marker '{}' not found", keyword))?;
+
+    let script_end = html[key_idx..]
+        .find("</script>")
+        .map(|i| key_idx + i)
+        .unwrap_or(html.len());
+
+    let script_start_tag_idx = html[..key_idx].rfind("<script").unwrap_or(0);
+
+    let script_content_start = html[script_start_tag_idx..key_idx]
+        .find('>')
+        .map(|i| script_start_tag_idx + i + 1)
+        .unwrap_or(script_start_tag_idx);
+
+    Ok((script_start_tag_idx, script_content_start, script_end))
+}
+
+/// Pulls the `integrity="..."` attribute's value out of a `<script ...>` opening tag's raw text.
+/// Only handles the quoted forms (`integrity="..."` / `integrity='...'`) real HTML emits.
+fn extract_integrity_attr(script_tag: &str) -> Option<String> {
+    let re = Regex::new(r#"integrity\s*=\s*"([^"]+)"|integrity\s*=\s*'([^']+)'"#).ok()?;
+    let caps = re.captures(script_tag)?;
+    caps.get(1).or_else(|| caps.get(2)).map(|m| m.as_str().to_string())
+}
+
+/// One `<alg>-<base64digest>` candidate out of an `integrity` attribute's (possibly
+/// space-separated) value.
+struct IntegrityCandidate {
+    algorithm: String,
+    digest: String,
+}
+
+/// Digest byte-strength per the SRI spec's "strongest metadata wins" precedence, used to pick
+/// which candidate to verify first when several are present.
+fn algorithm_strength(algorithm: &str) -> u8 {
+    match algorithm {
+        "sha512" => 3,
+        "sha384" => 2,
+        "sha256" => 1,
+        _ => 0,
+    }
+}
+
 impl CloudflareChallengeOptions {
     pub fn from_html(html: &str) -> Result<Self> {
         if let Ok(options) = Self::parse_with_ast(html) {
@@ -75,31 +314,27 @@ impl CloudflareChallengeOptions {
             }
             eprintln!("⚠️ AST parsing incomplete (missing critical fields), trying regex fallback...");
         }
-        
+
         Self::parse_with_regex(html)
     }
 
-    fn parse_with_ast(html: &str) -> Result<Self> {
-        let keyword = "_cf_chl_opt";
-        let key_idx = html
-            .find(keyword)
-            .ok_or_else(|| anyhow!("Marker '{}' not found", keyword))?;
-
-        let script_end = html[key_idx..]
-            .find("</script>")
-            .map(|i| key_idx + i)
-            .unwrap_or(html.len());
-
-        let script_start_tag_idx = html[..key_idx]
-            .rfind("<script")
-            .unwrap_or(0);
+    /// Same as [`Self::from_html`], but for a challenge page body that hasn't been decoded yet -
+    /// some origins hand the page back already gzip/deflate-compressed even though this client
+    /// never asked for it (see `task_client::decompress_body` for the same problem on the
+    /// response side). Sniffs a leading magic signature rather than trusting a declared
+    /// encoding, since there isn't one to trust at this call site.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let html = decode_challenge_body(bytes)?;
+        Self::from_html(&html)
+    }
 
-        let script_content_start = html[script_start_tag_idx..key_idx]
-            .find('>')
-            .map(|i| script_start_tag_idx + i + 1)
-            .unwrap_or(script_start_tag_idx); 
+    fn parse_with_ast(html: &str) -> Result<Self> {
+        let (script_start_tag_idx, script_content_start, script_end) =
+            locate_challenge_script(html)?;
 
         let source_code = &html[script_content_start..script_end];
+        let script_tag = &html[script_start_tag_idx..script_content_start];
+        let integrity = extract_integrity_attr(script_tag);
 
         let allocator = Allocator::default();
         let source_type = SourceType::default(); 
@@ -113,6 +348,7 @@ impl CloudflareChallengeOptions {
             candidates: Vec::new(),
             options: CloudflareChallengeOptions::default(),
             found_target: false,
+            arrays: collect_string_arrays(&ret.program.body),
         };
         
         visitor.visit_program(&ret.program);
@@ -125,10 +361,17 @@ impl CloudflareChallengeOptions {
         let mut opts = visitor.options;
         let candidates = visitor.candidates;
 
+        // 0. Key-based assignment first: if the obfuscator's property name survived (or the
+        // pass above resolved `eval_const` on an identifier key), trust it over any shape
+        // heuristic. Everything below only fills in whatever this leaves blank.
+        apply_key_based_mapping(&mut opts, &candidates);
+
         // 1. Extract Payload (c_arg) - The longest string
-        if let Some(payload) = candidates.iter().max_by_key(|s| s.len()) {
-            if payload.len() > 100 {
-                opts.c_arg = payload.clone();
+        if opts.c_arg.is_empty() {
+            if let Some((_, payload)) = candidates.iter().max_by_key(|(_, v)| v.len()) {
+                if payload.len() > 100 {
+                    opts.c_arg = payload.clone();
+                }
             }
         }
 
@@ -145,29 +388,29 @@ impl CloudflareChallengeOptions {
             eprintln!("🔍 Detected version signature in payload: {}", sig);
         }
 
-        // 3. Map other fields
-        for v in &candidates {
+        // 3. Map other fields still blank after key-based assignment
+        for (_, v) in &candidates {
             // Skip the payload itself
             if *v == opts.c_arg { continue; }
 
             // SiteKey
-            if v.starts_with("0x4") && v.len() < 35 {
+            if opts.site_key.is_empty() && v.starts_with("0x4") && v.len() < 35 {
                 opts.site_key = v.clone();
-            } 
+            }
             // cRay
-            else if v.len() == 16 && v.chars().all(|c| c.is_ascii_hexdigit()) {
+            else if opts.c_ray.is_empty() && v.len() == 16 && v.chars().all(|c| c.is_ascii_hexdigit()) {
                 opts.c_ray = v.clone();
             }
             // Zone
-            else if v.contains("cloudflare.com") {
+            else if opts.zone.is_empty() && v.contains("cloudflare.com") {
                 opts.zone = v.clone();
             }
             // Widget ID
-            else if v.len() == 5 && v.chars().all(|c| c.is_ascii_alphanumeric()) {
+            else if opts.widget_id.is_empty() && v.len() == 5 && v.chars().all(|c| c.is_ascii_alphanumeric()) {
                 opts.widget_id = v.clone();
             }
             // Mode
-            else if matches!(v.as_str(), "managed" | "non-interactive" | "invisible") {
+            else if opts.api_mode.is_empty() && matches!(v.as_str(), "managed" | "non-interactive" | "invisible") {
                 opts.api_mode = v.clone();
             }
             // Challenge Hash (ch) - CRITICAL FIX
@@ -184,7 +427,7 @@ impl CloudflareChallengeOptions {
 
         // 4. Fallback for ch if no signature matched (or payload parsing failed)
         if opts.ch.is_empty() {
-            for v in &candidates {
+            for (_, v) in &candidates {
                 if *v == opts.c_arg { continue; }
                 if v.len() > 50 && v.len() < 500 && !v.starts_with('0') { // Avoid api_rcv if possible
                      // Only take it if it looks like a hash (contains dashes/dots)
@@ -210,6 +453,7 @@ impl CloudflareChallengeOptions {
         }
 
         opts.turnstile_u = Self::extract_turnstile_u(html).unwrap_or_default();
+        opts.integrity = integrity;
 
         Ok(opts)
     }
@@ -230,18 +474,72 @@ impl CloudflareChallengeOptions {
                 options.c_arg = m.as_str().to_string();
             }
         }
-        
+
+        if let Ok((tag_start, content_start, _)) = locate_challenge_script(html) {
+            options.integrity = extract_integrity_attr(&html[tag_start..content_start]);
+        }
+
         Ok(options)
     }
 
     fn extract_turnstile_u(html: &str) -> Option<String> {
         let parts: Vec<&str> = html.split("chlTimeoutMs").collect();
         if parts.len() > 1 {
-             return Some("".to_string()); 
+             return Some("".to_string());
         }
         None
     }
 
+    /// Verifies `self.integrity` (if set) against the exact challenge-script byte range
+    /// `locate_challenge_script` finds in `html`. An `integrity` attribute can list several
+    /// space-separated `<alg>-<base64digest>` candidates - per the SRI spec this passes if *any*
+    /// one matches, so only the strongest-available algorithm is actually hashed; weaker
+    /// candidates alongside it exist for browsers that don't support the strong one. Returns
+    /// `Ok(true)` when there's no `integrity` attribute to check at all, since "nothing to
+    /// verify" isn't a failure.
+    pub fn verify_integrity(&self, html: &str) -> Result<bool> {
+        let Some(attr) = &self.integrity else {
+            return Ok(true);
+        };
+
+        let mut candidates: Vec<IntegrityCandidate> = attr
+            .split_whitespace()
+            .filter_map(|token| {
+                let (algorithm, digest) = token.split_once('-')?;
+                Some(IntegrityCandidate {
+                    algorithm: algorithm.to_ascii_lowercase(),
+                    digest: digest.to_string(),
+                })
+            })
+            .collect();
+
+        if candidates.is_empty() {
+            return Ok(true);
+        }
+
+        candidates.sort_by_key(|c| std::cmp::Reverse(algorithm_strength(&c.algorithm)));
+
+        let (_, content_start, content_end) = locate_challenge_script(html)?;
+        let script_bytes = html[content_start..content_end].as_bytes();
+
+        for candidate in &candidates {
+            let computed = match candidate.algorithm.as_str() {
+                "sha256" => STANDARD.encode(Sha256::digest(script_bytes)),
+                "sha384" => STANDARD.encode(Sha384::digest(script_bytes)),
+                "sha512" => STANDARD.encode(Sha512::digest(script_bytes)),
+                other => {
+                    return Err(Error::UnsupportedIntegrityAlgorithm(other.to_string()).into());
+                }
+            };
+
+            if computed == candidate.digest {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
     /// Extract fields from the orchestrate API response
     pub fn extract_from_orchestrate(orchestrate_text: &str) -> Result<(String, String)> {
         eprintln!("\n=== Analyzing Orchestrate Response ===");
@@ -299,4 +597,65 @@ impl CloudflareChallengeOptions {
 
         Ok((ch, url))
     }
+
+    /// Structured replacement for [`Self::extract_from_orchestrate`]'s positional `(ch, url)`
+    /// tuple: tries `serde_json` against the response body first (plain JSON, or `window.
+    /// _cf_chl_opt = {...}` with the assignment stripped), and only drops to the regex-based
+    /// `extract_from_orchestrate` once both JSON attempts fail to parse. A response JSON can't
+    /// parse (malformed, truncated, wrapped in something other than `_cf_chl_opt`) far more
+    /// often than it can fail type-check, so this is "JSON when we can, regex when we must"
+    /// rather than the other way around.
+    pub fn parse_orchestrate_response(orchestrate_text: &str) -> Result<OrchestrateResponse> {
+        let trimmed = orchestrate_text.trim();
+
+        if let Ok(response) = serde_json::from_str::<OrchestrateResponse>(trimmed) {
+            return Ok(response);
+        }
+
+        if let Some(object_literal) = extract_cf_chl_opt_assignment(trimmed) {
+            if let Ok(response) = serde_json::from_str::<OrchestrateResponse>(object_literal) {
+                return Ok(response);
+            }
+        }
+
+        let (ch, url) = Self::extract_from_orchestrate(orchestrate_text)?;
+        Ok(OrchestrateResponse {
+            ch: (!ch.is_empty()).then_some(ch),
+            url: (!url.is_empty()).then_some(url),
+            ..Default::default()
+        })
+    }
+}
+
+/// Strips a `window._cf_chl_opt = {...};` (or bare `_cf_chl_opt = {...}`) assignment down to just
+/// the object literal, so it can be handed to `serde_json` directly. Returns `None` if the text
+/// isn't wrapped this way at all.
+fn extract_cf_chl_opt_assignment(text: &str) -> Option<&str> {
+    let keyword = "_cf_chl_opt";
+    let key_idx = text.find(keyword)?;
+    let brace_start = text[key_idx..].find('{').map(|i| key_idx + i)?;
+    let brace_end = text.rfind('}')?;
+    if brace_end <= brace_start {
+        return None;
+    }
+    Some(&text[brace_start..=brace_end])
+}
+
+/// Typed view of the orchestrate endpoint's response, built by
+/// [`CloudflareChallengeOptions::parse_orchestrate_response`]. Every field is optional since the
+/// endpoint only ever returns a subset depending on challenge stage, and `#[serde(alias = ...)]`
+/// covers the camelCase/short-name variants Cloudflare emits interchangeably (`cH` vs `ch`, etc).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OrchestrateResponse {
+    #[serde(alias = "cH")]
+    pub ch: Option<String>,
+    pub url: Option<String>,
+    pub flow: Option<String>,
+    pub token: Option<String>,
+    #[serde(alias = "cRay")]
+    pub ray: Option<String>,
+    #[serde(alias = "chlApiTimeoutEncountered")]
+    pub timeout_encountered: Option<String>,
+    #[serde(alias = "chlTimeoutMs")]
+    pub timeout_ms: Option<u64>,
 }
\ No newline at end of file
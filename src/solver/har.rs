@@ -0,0 +1,225 @@
+use rquest::header::HeaderMap;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Minimal HAR 1.2 (http://www.softwareishard.com/blog/har-12-spec/) document, just the
+/// fields a replay/debugging tool (Chrome DevTools, HAR viewers) actually reads. Recorded
+/// alongside a solve run so a failed challenge can be diffed request-by-request against a
+/// real browser's capture instead of re-running with `eprintln!` debugging.
+#[derive(Debug, Serialize)]
+pub struct HarLog {
+    log: HarLogInner,
+}
+
+#[derive(Debug, Serialize)]
+struct HarLogInner {
+    version: &'static str,
+    creator: HarCreator,
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct HarCreator {
+    name: &'static str,
+    version: &'static str,
+}
+
+/// Formats a `SystemTime` as an RFC 3339 / ISO 8601 UTC timestamp without pulling in a date
+/// library just for this one field, using the standard days-since-epoch civil date algorithm.
+fn format_rfc3339(time: SystemTime) -> String {
+    let duration = time.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    let secs = duration.as_secs();
+    let millis = duration.subsec_millis();
+
+    let days = (secs / 86400) as i64;
+    let time_of_day = secs % 86400;
+    let (hour, minute, second) = (time_of_day / 3600, (time_of_day % 3600) / 60, time_of_day % 60);
+
+    // Howard Hinnant's days-from-civil, inverted: days since 1970-01-01 -> (y, m, d).
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = (z - era * 146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = if m <= 2 { y + 1 } else { y };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        y, m, d, hour, minute, second, millis
+    )
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HarEntry {
+    #[serde(rename = "startedDateTime")]
+    started_date_time: String,
+    time: f64,
+    request: HarRequest,
+    response: HarResponse,
+    timings: HarTimings,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct HarRequest {
+    method: String,
+    url: String,
+    #[serde(rename = "httpVersion")]
+    http_version: &'static str,
+    headers: Vec<HarHeader>,
+    #[serde(rename = "queryString")]
+    query_string: Vec<HarHeader>,
+    #[serde(rename = "bodySize")]
+    body_size: i64,
+    #[serde(rename = "postData", skip_serializing_if = "Option::is_none")]
+    post_data: Option<HarPostData>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct HarPostData {
+    #[serde(rename = "mimeType")]
+    mime_type: String,
+    text: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct HarResponse {
+    status: u16,
+    #[serde(rename = "statusText")]
+    status_text: String,
+    #[serde(rename = "httpVersion")]
+    http_version: &'static str,
+    headers: Vec<HarHeader>,
+    content: HarContent,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    #[serde(rename = "headersSize")]
+    headers_size: i64,
+    #[serde(rename = "bodySize")]
+    body_size: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct HarContent {
+    size: i64,
+    #[serde(rename = "mimeType")]
+    mime_type: String,
+    text: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct HarHeader {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct HarTimings {
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+fn headers_to_har(headers: &HeaderMap) -> Vec<HarHeader> {
+    headers
+        .iter()
+        .map(|(name, value)| HarHeader {
+            name: name.to_string(),
+            value: value.to_str().unwrap_or("").to_string(),
+        })
+        .collect()
+}
+
+/// Accumulates `HarEntry`s across a solve run. Cheap to clone (just an `Arc` in callers) and
+/// safe to share between the sequential calls `TaskClient` makes, since every call happens on
+/// one task anyway - the `Mutex` is only here so recording can't ever panic the solve path if
+/// that assumption changes later.
+#[derive(Debug, Default)]
+pub struct HarRecorder {
+    entries: Mutex<Vec<HarEntry>>,
+}
+
+impl HarRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one request/response round-trip. `elapsed` is the full round-trip time; we
+    /// don't have Cloudflare's server-side timing breakdown so everything is folded into `wait`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn record(
+        &self,
+        method: &str,
+        url: &str,
+        request_headers: &HeaderMap,
+        request_body: Option<&str>,
+        status: u16,
+        status_text: &str,
+        response_headers: &HeaderMap,
+        response_body: &str,
+        elapsed: Duration,
+    ) {
+        let entry = HarEntry {
+            started_date_time: format_rfc3339(SystemTime::now()),
+            time: elapsed.as_secs_f64() * 1000.0,
+            request: HarRequest {
+                method: method.to_string(),
+                url: url.to_string(),
+                http_version: "HTTP/2.0",
+                headers: headers_to_har(request_headers),
+                query_string: Vec::new(),
+                body_size: request_body.map(|b| b.len() as i64).unwrap_or(0),
+                post_data: request_body.map(|body| HarPostData {
+                    mime_type: "text/plain;charset=UTF-8".to_string(),
+                    text: body.to_string(),
+                }),
+            },
+            response: HarResponse {
+                status,
+                status_text: status_text.to_string(),
+                http_version: "HTTP/2.0",
+                headers: headers_to_har(response_headers),
+                content: HarContent {
+                    size: response_body.len() as i64,
+                    mime_type: response_headers
+                        .get("content-type")
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or("")
+                        .to_string(),
+                    text: response_body.to_string(),
+                },
+                redirect_url: String::new(),
+                headers_size: -1,
+                body_size: response_body.len() as i64,
+            },
+            timings: HarTimings {
+                send: 0.0,
+                wait: elapsed.as_secs_f64() * 1000.0,
+                receive: 0.0,
+            },
+        };
+
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.push(entry);
+        }
+    }
+
+    /// Snapshots the recorded entries into a serializable HAR 1.2 log.
+    pub fn to_har_log(&self) -> HarLog {
+        let entries = self.entries.lock().map(|e| e.clone()).unwrap_or_default();
+        HarLog {
+            log: HarLogInner {
+                version: "1.2",
+                creator: HarCreator {
+                    name: "cf-turnstile-solver",
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                entries,
+            },
+        }
+    }
+}
@@ -1,12 +1,15 @@
-use crate::solver::task::TurnstileTask;
+use crate::solver::task::{SolveResult, TurnstileTask};
 use crate::solver::user_fingerprint::Fingerprint;
 use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
 use rand::{rng, Rng};
 use std::sync::Arc;
 use tokio::fs;
+use tokio::task::JoinHandle;
 
 pub(crate) mod challenge;
 pub mod entries;
+pub mod har;
 pub mod keys;
 mod performance;
 pub mod task;
@@ -15,6 +18,7 @@ pub mod user_fingerprint;
 mod utils;
 pub mod vm_parser;
 mod timezone;
+mod webgl_coherence;
 
 #[derive(Debug, Clone)]
 pub struct VersionInfo {
@@ -39,7 +43,13 @@ impl TurnstileSolver {
         let mut fps = Vec::with_capacity(raw_values.len());
         // Correction Warning: suppression de .enumerate() car 'i' était inutilisé
         for v in raw_values {
-            if let Ok(fp) = serde_json::from_value::<Fingerprint>(v) {
+            if let Ok(mut fp) = serde_json::from_value::<Fingerprint>(v) {
+                if webgl_coherence::validate_or_repair(&mut fp) {
+                    eprintln!(
+                        "⚠️  fingerprint at {} had an incoherent WebGL/WebGPU profile, repaired at load time",
+                        path
+                    );
+                }
                 fps.push(fp);
             }
         }
@@ -80,4 +90,168 @@ impl TurnstileSolver {
         let idx = rng().random_range(0..self.fingerprints.len());
         &self.fingerprints[idx]
     }
+}
+
+/// The construction step every submission path shares: pick a fingerprint and build the
+/// `TurnstileTask`. `SyncSolverClient`/`AsyncSolverClient` below only differ in what they do
+/// with the task once `create_task` hands it back, which is also why a caller wiring in a mock
+/// transport for tests only needs to implement this one method.
+#[async_trait]
+pub trait SolverClient {
+    async fn create_task(
+        &self,
+        site_key: String,
+        href: String,
+        action: Option<String>,
+        c_data: Option<String>,
+    ) -> Result<TurnstileTask>;
+}
+
+#[async_trait]
+impl SolverClient for TurnstileSolver {
+    async fn create_task(
+        &self,
+        site_key: String,
+        href: String,
+        action: Option<String>,
+        c_data: Option<String>,
+    ) -> Result<TurnstileTask> {
+        self.create_task(site_key, href, action, c_data).await
+    }
+}
+
+/// Blocking submission: drives the task to completion and, since a failure often just means
+/// the selected fingerprint/script pairing was stale rather than the site being unsolvable,
+/// retries once with a freshly created task (a new fingerprint pick) before giving up.
+pub trait SyncSolverClient: SolverClient {
+    fn solve_challenge_blocking(
+        &self,
+        site_key: String,
+        href: String,
+        action: Option<String>,
+        c_data: Option<String>,
+    ) -> Result<SolveResult>;
+}
+
+impl SyncSolverClient for BlockingClient {
+    fn solve_challenge_blocking(
+        &self,
+        site_key: String,
+        href: String,
+        action: Option<String>,
+        c_data: Option<String>,
+    ) -> Result<SolveResult> {
+        self.runtime.block_on(async {
+            let mut last_err = None;
+            for attempt in 0..2 {
+                let mut task = self
+                    .solver
+                    .create_task(site_key.clone(), href.clone(), action.clone(), c_data.clone())
+                    .await?;
+
+                match task.solve().await {
+                    Ok(result) => return Ok(result),
+                    Err(err) if attempt == 0 => {
+                        eprintln!(
+                            "⚠️  solve attempt failed, retrying once with a fresh fingerprint: {err}"
+                        );
+                        last_err = Some(err);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            Err(last_err.expect("loop always records an error before exhausting its attempts"))
+        })
+    }
+}
+
+/// Fire-and-forget submission: only awaits task construction inline, then spawns the actual
+/// solve so the caller gets control back immediately. Unlike `AsyncClient::solve_challenge`,
+/// which awaits the whole round trip, this is for high-throughput callers that want to submit
+/// many challenges without serializing on each one's completion - the returned `JoinHandle` can
+/// be awaited later or dropped.
+#[async_trait]
+pub trait AsyncSolverClient: SolverClient {
+    async fn submit(
+        &self,
+        site_key: String,
+        href: String,
+        action: Option<String>,
+        c_data: Option<String>,
+    ) -> Result<JoinHandle<Result<SolveResult>>>;
+}
+
+#[async_trait]
+impl AsyncSolverClient for TurnstileSolver {
+    async fn submit(
+        &self,
+        site_key: String,
+        href: String,
+        action: Option<String>,
+        c_data: Option<String>,
+    ) -> Result<JoinHandle<Result<SolveResult>>> {
+        let mut task = self.create_task(site_key, href, action, c_data).await?;
+        Ok(tokio::spawn(async move { task.solve().await }))
+    }
+}
+
+/// Creates a task and drives it to completion in one call. `TurnstileSolver` implements this
+/// natively; `BlockingClient` below adapts the same flow for callers outside an async context.
+#[async_trait]
+pub trait AsyncClient {
+    async fn solve_challenge(
+        &self,
+        site_key: String,
+        href: String,
+        action: Option<String>,
+        c_data: Option<String>,
+    ) -> Result<SolveResult>;
+}
+
+#[async_trait]
+impl AsyncClient for TurnstileSolver {
+    async fn solve_challenge(
+        &self,
+        site_key: String,
+        href: String,
+        action: Option<String>,
+        c_data: Option<String>,
+    ) -> Result<SolveResult> {
+        let mut task = self.create_task(site_key, href, action, c_data).await?;
+        task.solve().await
+    }
+}
+
+/// Blocking adapter over [`AsyncClient`] for callers that don't want to manage their own async
+/// runtime. Builds a single dedicated runtime once and reuses it for every call, the same way
+/// `reqwest::blocking` sits on top of the async `reqwest::Client`.
+pub struct BlockingClient {
+    solver: TurnstileSolver,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl BlockingClient {
+    pub fn new(solver: TurnstileSolver) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to build blocking runtime")?;
+
+        Ok(Self { solver, runtime })
+    }
+
+    pub fn solve_challenge(
+        &self,
+        site_key: impl Into<String>,
+        href: impl Into<String>,
+        action: Option<String>,
+        c_data: Option<String>,
+    ) -> Result<SolveResult> {
+        self.runtime.block_on(self.solver.solve_challenge(
+            site_key.into(),
+            href.into(),
+            action,
+            c_data,
+        ))
+    }
 }
\ No newline at end of file
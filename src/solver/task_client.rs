@@ -1,5 +1,6 @@
 use crate::reverse::encryption::decrypt_cloudflare_response;
 use crate::solver::challenge::CloudflareChallengeOptions;
+use crate::solver::har::HarRecorder;
 use crate::solver::performance::{PerformanceEntry, PerformanceResourceEntry};
 use crate::solver::timezone::get_timezone;
 use crate::solver::user_fingerprint::Headers;
@@ -7,42 +8,324 @@ use crate::solver::utils::imprecise_performance_now_value;
 use crate::solver::VersionInfo;
 use anyhow::{bail};
 use rand::Rng;
+use rquest::cookie::Jar;
 use rquest::header::{HeaderMap, HeaderName, HeaderValue};
 use rquest::{Client, EmulationProviderFactory, Version};
 use rquest_util::Emulation::Chrome136;
-use rquest_util::EmulationOS::Windows;
-use rquest_util::{EmulationOption};
+use rquest_util::{Emulation, EmulationOS, EmulationOption};
+use rustc_hash::FxHashMap;
+use sha2::{Digest, Sha256};
 use std::io::Read;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant};
 use url::Url;
-use regex::Regex; 
+use regex::Regex;
 
 pub struct TaskClient {
     client: Client,
     host: String,
     branch: String,
     solve_url: Option<String>,
+    /// Cookies Cloudflare sets over the challenge flow (`cf_clearance`, `cf_chl_rc_m`, ...) have
+    /// to survive across every call this struct makes - orchestrate, image fetches, payload
+    /// posts - or the challenge server stops recognizing the session partway through. This is
+    /// the jar baked into `client` at construction time (see `pooled_client`); every
+    /// request/response round-trips through it automatically.
+    cookie_jar: Arc<Jar>,
+    /// HAR 1.2 recorder for the full challenge flow, populated whenever one is supplied to
+    /// `new`. `None` by default so normal solves don't pay for buffering every response body.
+    har: Option<Arc<HarRecorder>>,
+    retry: RetryConfig,
+    policy: SolvePolicy,
+    /// Whether to print the `ChallengeReport` from each fetched challenge page to stderr. Off
+    /// by default even in debug builds, since the report is meant to be consumed as JSON by a
+    /// monitoring pipeline, not to spam the terminal.
+    debug_diagnostics: bool,
+    header_profile: HeaderProfile,
+}
+
+/// Exponential backoff policy for transient network failures (`send()` transport errors, not
+/// HTTP error statuses - those already carry enough context for the caller to act on directly
+/// without a retry).
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryConfig {
+    fn delay_for(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(1u32 << attempt.min(10))
+            .min(self.max_delay)
+    }
+}
+
+/// Browser/OS combination `TaskClient` emulates at the TLS/HTTP2 fingerprint level. Cloudflare
+/// fingerprints more than the `User-Agent` string, so the `rquest` emulation and the header
+/// order sent on the wire need to agree on which browser they're pretending to be - hence this
+/// being one selectable unit rather than two independent knobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmulationProfile {
+    Chrome136Windows,
+    Chrome136MacOs,
+    Chrome136Linux,
+}
+
+impl Default for EmulationProfile {
+    fn default() -> Self {
+        EmulationProfile::Chrome136Windows
+    }
+}
+
+impl EmulationProfile {
+    fn emulation(self) -> Emulation {
+        match self {
+            EmulationProfile::Chrome136Windows
+            | EmulationProfile::Chrome136MacOs
+            | EmulationProfile::Chrome136Linux => Chrome136,
+        }
+    }
+
+    fn emulation_os(self) -> EmulationOS {
+        match self {
+            EmulationProfile::Chrome136Windows => EmulationOS::Windows,
+            EmulationProfile::Chrome136MacOs => EmulationOS::MacOS,
+            EmulationProfile::Chrome136Linux => EmulationOS::Linux,
+        }
+    }
+
+    /// The `HeaderProfile` matching this TLS/HTTP2 emulation target, so the header-order and
+    /// client-hint fingerprint agree with the Chrome identity `emulation`/`emulation_os` claim.
+    fn header_profile(self) -> HeaderProfile {
+        match self {
+            EmulationProfile::Chrome136Windows
+            | EmulationProfile::Chrome136MacOs
+            | EmulationProfile::Chrome136Linux => HeaderProfile::chrome_desktop(),
+        }
+    }
+}
+
+/// Tunables for `TaskClient::new_with_config` that go beyond what a referrer/headers pair can
+/// express. Bundled into one struct instead of piling on more positional `new()` parameters,
+/// since later clients layer on still more configuration (emulation profile, header order).
+#[derive(Default)]
+pub struct TaskClientConfig {
+    pub proxy: Option<ProxyConfig>,
+    pub har: Option<Arc<HarRecorder>>,
+    pub timeout: Option<Duration>,
+    pub retry: Option<RetryConfig>,
+    pub profile: EmulationProfile,
+    pub policy: SolvePolicy,
+    pub debug_diagnostics: bool,
+}
+
+/// Distinguishes a deliberate `SolvePolicy` rejection from the generic failures `anyhow::Error`
+/// covers everywhere else in this module, so callers can match on it instead of string-matching
+/// an error message.
+#[derive(Debug)]
+pub enum Error {
+    PolicyBlocked { host: String, site_key: String },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::PolicyBlocked { host, site_key } => write!(
+                f,
+                "solve policy blocked host '{host}' / site_key '{site_key}'"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// One domain/site-key matching rule in a `SolvePolicy`. `*.example.com` matches `example.com`
+/// and any of its subdomains; anything else matches the value exactly.
+#[derive(Debug, Clone)]
+pub struct Pattern(String);
+
+impl Pattern {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self(pattern.into())
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self.0.strip_prefix("*.") {
+            Some(suffix) => value == suffix || value.ends_with(&format!(".{suffix}")),
+            None => value == self.0,
+        }
+    }
+}
+
+/// Restricts which (referrer host, site key) pairs a `TaskClient` will solve for. Lets an
+/// operator running a shared proxy pool scope a deployed solver to known customer domains
+/// instead of silently solving for whatever site a misrouted request names. An empty `allow`
+/// means "allow anything not explicitly denied"; a non-empty `allow` switches to allow-list mode.
+#[derive(Debug, Clone, Default)]
+pub struct SolvePolicy {
+    pub allow: Vec<Pattern>,
+    pub deny: Vec<Pattern>,
+}
+
+impl SolvePolicy {
+    pub fn check(&self, host: &str, site_key: &str) -> Result<(), Error> {
+        let blocked = || Error::PolicyBlocked {
+            host: host.to_string(),
+            site_key: site_key.to_string(),
+        };
+
+        if self.deny.iter().any(|p| p.matches(host) || p.matches(site_key)) {
+            return Err(blocked());
+        }
+
+        if !self.allow.is_empty()
+            && !self
+                .allow
+                .iter()
+                .any(|p| p.matches(host) || p.matches(site_key))
+        {
+            return Err(blocked());
+        }
+
+        Ok(())
+    }
 }
 
 impl TaskClient {
     pub(crate) fn new(
         referrer: String,
         headers: Headers,
+        proxy: Option<ProxyConfig>,
     ) -> Result<TaskClient, anyhow::Error> {
-        let emulation = EmulationOption::builder()
-            .emulation(Chrome136)
-            .emulation_os(Windows)
-            .build();
+        Self::new_with_config(
+            referrer,
+            headers,
+            TaskClientConfig {
+                proxy,
+                ..Default::default()
+            },
+        )
+    }
 
-        let client = build_client(emulation, None, headers)?;
+    pub(crate) fn new_with_har(
+        referrer: String,
+        headers: Headers,
+        proxy: Option<ProxyConfig>,
+        har: Option<Arc<HarRecorder>>,
+    ) -> Result<TaskClient, anyhow::Error> {
+        Self::new_with_config(
+            referrer,
+            headers,
+            TaskClientConfig {
+                proxy,
+                har,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub(crate) fn new_with_config(
+        referrer: String,
+        headers: Headers,
+        config: TaskClientConfig,
+    ) -> Result<TaskClient, anyhow::Error> {
+        let timeout = config.timeout.unwrap_or(Duration::from_secs(15));
+        let header_profile = config.profile.header_profile();
+        let pooled = pooled_client(config.profile, config.proxy, headers, timeout)?;
         Ok(Self {
             host: get_referrer_host(referrer.as_str())?,
-            client,
+            client: pooled.client.clone(),
             branch: "b".to_string(),
             solve_url: None,
+            cookie_jar: pooled.cookie_jar.clone(),
+            har: config.har,
+            retry: config.retry.unwrap_or_default(),
+            policy: config.policy,
+            debug_diagnostics: config.debug_diagnostics,
+            header_profile,
         })
     }
 
+    /// Retries `f` (a thunk that builds and sends a fresh request each call, since a sent
+    /// `RequestBuilder` can't be replayed) with exponential backoff on transport-level
+    /// failures. HTTP error statuses are the caller's responsibility - only failures to get a
+    /// response at all (timeouts, connection resets) are retried here.
+    async fn retry_with_backoff<F, Fut, T>(&self, mut f: F) -> Result<T, rquest::Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, rquest::Error>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match f().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.retry.max_retries => {
+                    let delay = self.retry.delay_for(attempt);
+                    eprintln!(
+                        "⚠️  Request failed ({e}), retrying in {:?} (attempt {}/{})",
+                        delay,
+                        attempt + 1,
+                        self.retry.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Records one request/response round-trip into the HAR log, if recording is enabled.
+    #[allow(clippy::too_many_arguments)]
+    fn record_har(
+        &self,
+        method: &str,
+        url: &str,
+        request_headers: &HeaderMap,
+        request_body: Option<&str>,
+        status: u16,
+        status_text: &str,
+        response_headers: &HeaderMap,
+        response_body: &str,
+        elapsed: Duration,
+    ) {
+        if let Some(har) = &self.har {
+            har.record(
+                method,
+                url,
+                request_headers,
+                request_body,
+                status,
+                status_text,
+                response_headers,
+                response_body,
+                elapsed,
+            );
+        }
+    }
+
+    /// Returns the `Cookie` header value the jar would attach to a request against `url`, if
+    /// any cookies have been captured for it yet.
+    pub(crate) fn cookies_for(&self, url: &str) -> Option<String> {
+        let parsed = Url::parse(url).ok()?;
+        self.cookie_jar
+            .cookies(&parsed)
+            .and_then(|v| v.to_str().ok().map(|s| s.to_string()))
+    }
+
 
     fn extract_or_generate_ch(
         &self,
@@ -212,25 +495,36 @@ pub(crate) async fn initialize_solve(
     &mut self,
     site_key: &str,
 ) -> Result<(String, String, CloudflareChallengeOptions), anyhow::Error> {
+    let policy_host = Url::parse(&self.host)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_else(|| self.host.clone());
+    self.policy.check(&policy_host, site_key)?;
+
     self.set_get_html_headers_order();
     let solve_url = generate_solve_url(self.branch.as_str(), site_key);
 
     eprintln!("🔍 Fetching: {}", solve_url);
 
+    let t = Instant::now();
     let response = self
-        .client
-        .get(solve_url.as_str())
-        .header("Upgrade-Insecure-Requests", "1")
-        .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7")
-        .header("Sec-Fetch-Site", "cross-site")
-        .header("Sec-Fetch-Mode", "navigate")
-        .header("Sec-Fetch-Dest", "iframe")
-        .header("Referer", &self.host)
-        .header("Priority", "u=0, i")
-        .send()
+        .retry_with_backoff(|| {
+            self.client
+                .get(solve_url.as_str())
+                .header("Upgrade-Insecure-Requests", "1")
+                .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7")
+                .header("Sec-Fetch-Site", "cross-site")
+                .header("Sec-Fetch-Mode", "navigate")
+                .header("Sec-Fetch-Dest", "iframe")
+                .header("Referer", &self.host)
+                .header("Priority", "u=0, i")
+                .send()
+        })
         .await?;
 
     eprintln!("📡 Status: {}", response.status());
+    let response_status = response.status();
+    let response_headers = response.headers().clone();
 
     if !response.status().is_success() {
         bail!(
@@ -262,15 +556,32 @@ pub(crate) async fn initialize_solve(
         .map_err(|e| anyhow::anyhow!("Decompression failed: {}", e))?;
     let text = String::from_utf8(decompressed)?;
 
+    let challenge_digest = record_challenge_digest(self.branch.as_str(), site_key, &text);
+    eprintln!("🔑 Challenge script sha256: {}", challenge_digest);
+
+    self.record_har(
+        "GET",
+        solve_url.as_str(),
+        &HeaderMap::new(),
+        None,
+        response_status.as_u16(),
+        response_status.canonical_reason().unwrap_or(""),
+        &response_headers,
+        &text,
+        t.elapsed(),
+    );
+
     // Save for debugging
     #[cfg(debug_assertions)]
     {
         use std::fs;
         fs::write("debug_turnstile.html", &text).ok();
         eprintln!("💾 Saved response to debug_turnstile.html");
-        
-        // Print debug info
-        debug_html_response(&text, cf_ray_header.as_deref());
+    }
+
+    let challenge_report = build_challenge_report(&text, cf_ray_header.as_deref());
+    if self.debug_diagnostics {
+        eprintln!("{}", challenge_report);
     }
 
     // Parse the HTML
@@ -372,18 +683,22 @@ pub(crate) async fn initialize_solve(
         );
 
         let response = self
-            .client
-            .get(&url)
-            .header("Accept", "*/*")
-            .header("Sec-Fetch-Site", "same-origin")
-            .header("Sec-Fetch-Mode", "no-cors")
-            .header("Sec-Fetch-Dest", "script")
-            .header("Referer", self.solve_url.as_ref().unwrap())
-            .header("Priority", "u=1")
-            .redirect(rquest::redirect::Policy::none())
-            .send()
+            .retry_with_backoff(|| {
+                self.client
+                    .get(&url)
+                    .header("Accept", "*/*")
+                    .header("Sec-Fetch-Site", "same-origin")
+                    .header("Sec-Fetch-Mode", "no-cors")
+                    .header("Sec-Fetch-Dest", "script")
+                    .header("Referer", self.solve_url.as_ref().unwrap())
+                    .header("Priority", "u=1")
+                    .redirect(rquest::redirect::Policy::none())
+                    .send()
+            })
             .await?;
 
+        let response_status = response.status();
+        let response_headers = response.headers().clone();
         let content_encoding = response
             .headers()
             .get("Content-Encoding")
@@ -392,8 +707,22 @@ pub(crate) async fn initialize_solve(
             .to_str()?
             .to_string();
         let bytes = response.bytes().await?;
-        let decompressed = decompress_body(bytes.as_ref(), &content_encoding).unwrap();
+        let decompressed = decompress_body(bytes.as_ref(), &content_encoding)
+            .map_err(|e| anyhow::anyhow!("Decompression failed ({}): {}", content_encoding, e))?;
         let text = String::from_utf8(decompressed)?;
+
+        self.record_har(
+            "GET",
+            &url,
+            &HeaderMap::new(),
+            None,
+            response_status.as_u16(),
+            response_status.canonical_reason().unwrap_or(""),
+            &response_headers,
+            &text,
+            t.elapsed(),
+        );
+
         Ok((
             PerformanceEntry::Resource(PerformanceResourceEntry {
                 r#type: "r".to_string(),
@@ -454,7 +783,8 @@ pub(crate) async fn initialize_solve(
             .to_str()?
             .to_string();
         let bytes = response.bytes().await?;
-        let decompressed = decompress_body(bytes.as_ref(), &content_encoding).unwrap();
+        let decompressed = decompress_body(bytes.as_ref(), &content_encoding)
+            .map_err(|e| anyhow::anyhow!("Decompression failed ({}): {}", content_encoding, e))?;
 
         Ok((
             PerformanceEntry::Resource(PerformanceResourceEntry {
@@ -534,21 +864,23 @@ pub(crate) async fn initialize_solve(
 
         let t = Instant::now();
         let response = self
-            .client
-            .post(&url)
-            .header("Content-Length", compressed_payload.len().to_string())
-            .header("Content-Type", "text/plain;charset=UTF-8")
-            .header("cf-chl", ch)
-            .header("cf-chl-ra", "0")
-            .header("Accept", "*/*")
-            .header("Origin", format!("https://{zone}"))
-            .header("Sec-Fetch-Site", "same-origin")
-            .header("Sec-Fetch-Mode", "cors")
-            .header("Sec-Fetch-Dest", "empty")
-            .header("Referer", self.solve_url.as_ref().unwrap())
-            .header("Priority", "u=2")
-            .body(compressed_payload.clone())
-            .send()
+            .retry_with_backoff(|| {
+                self.client
+                    .post(&url)
+                    .header("Content-Length", compressed_payload.len().to_string())
+                    .header("Content-Type", "text/plain;charset=UTF-8")
+                    .header("cf-chl", ch)
+                    .header("cf-chl-ra", "0")
+                    .header("Accept", "*/*")
+                    .header("Origin", format!("https://{zone}"))
+                    .header("Sec-Fetch-Site", "same-origin")
+                    .header("Sec-Fetch-Mode", "cors")
+                    .header("Sec-Fetch-Dest", "empty")
+                    .header("Referer", self.solve_url.as_ref().unwrap())
+                    .header("Priority", "u=2")
+                    .body(compressed_payload.clone())
+                    .send()
+            })
             .await?;
 
         if response.status() != 200 {
@@ -559,6 +891,8 @@ pub(crate) async fn initialize_solve(
             ));
         }
 
+        let response_status = response.status();
+        let response_headers = response.headers().clone();
         let content_encoding = response
             .headers()
             .get("Content-Encoding")
@@ -567,9 +901,22 @@ pub(crate) async fn initialize_solve(
             .to_str()?
             .to_string();
         let bytes = response.bytes().await?;
-        let decompressed = decompress_body(bytes.as_ref(), &content_encoding).unwrap();
+        let decompressed = decompress_body(bytes.as_ref(), &content_encoding)
+            .map_err(|e| anyhow::anyhow!("Decompression failed ({}): {}", content_encoding, e))?;
         let text = String::from_utf8(decompressed)?;
 
+        self.record_har(
+            "POST",
+            &url,
+            &HeaderMap::new(),
+            Some(compressed_payload.as_str()),
+            response_status.as_u16(),
+            response_status.canonical_reason().unwrap_or(""),
+            &response_headers,
+            &text,
+            t.elapsed(),
+        );
+
         Ok((
             PerformanceEntry::Resource(PerformanceResourceEntry {
                 r#type: "r".to_string(),
@@ -596,22 +943,25 @@ pub(crate) async fn initialize_solve(
         let parsed = Url::parse(url)?;
         self.set_post_headers_order();
 
+        let t = Instant::now();
         let response = self
-            .client
-            .post(url)
-            .header("Content-Length", compressed_payload.len().to_string())
-            .header("Content-Type", "text/plain;charset=UTF-8")
-            .header("cf-chl", chl)
-            .header("cf-chl-ra", "0")
-            .header("Accept", "*/*")
-            .header("Origin", format!("https://{}", parsed.host().unwrap()))
-            .header("Sec-Fetch-Site", "same-origin")
-            .header("Sec-Fetch-Mode", "cors")
-            .header("Sec-Fetch-Dest", "empty")
-            .header("Referer", self.solve_url.as_ref().unwrap())
-            .header("Priority", "u=1, i")
-            .body(compressed_payload)
-            .send()
+            .retry_with_backoff(|| {
+                self.client
+                    .post(url)
+                    .header("Content-Length", compressed_payload.len().to_string())
+                    .header("Content-Type", "text/plain;charset=UTF-8")
+                    .header("cf-chl", chl)
+                    .header("cf-chl-ra", "0")
+                    .header("Accept", "*/*")
+                    .header("Origin", format!("https://{}", parsed.host().unwrap()))
+                    .header("Sec-Fetch-Site", "same-origin")
+                    .header("Sec-Fetch-Mode", "cors")
+                    .header("Sec-Fetch-Dest", "empty")
+                    .header("Referer", self.solve_url.as_ref().unwrap())
+                    .header("Priority", "u=1, i")
+                    .body(compressed_payload.clone())
+                    .send()
+            })
             .await?;
 
         if response.status() != 200 {
@@ -622,6 +972,8 @@ pub(crate) async fn initialize_solve(
             );
         }
 
+        let response_status = response.status();
+        let response_headers = response.headers().clone();
         let content_encoding = response
             .headers()
             .get("Content-Encoding")
@@ -630,9 +982,22 @@ pub(crate) async fn initialize_solve(
             .to_str()?
             .to_string();
         let bytes = response.bytes().await?;
-        let decompressed = decompress_body(bytes.as_ref(), &content_encoding).unwrap();
+        let decompressed = decompress_body(bytes.as_ref(), &content_encoding)
+            .map_err(|e| anyhow::anyhow!("Decompression failed ({}): {}", content_encoding, e))?;
         let text = String::from_utf8(decompressed)?;
 
+        self.record_har(
+            "POST",
+            url,
+            &HeaderMap::new(),
+            Some(compressed_payload.as_str()),
+            response_status.as_u16(),
+            response_status.canonical_reason().unwrap_or(""),
+            &response_headers,
+            &text,
+            t.elapsed(),
+        );
+
         decrypt_cloudflare_response(c_ray, &text)
     }
 
@@ -640,8 +1005,110 @@ pub(crate) async fn initialize_solve(
         &self.branch
     }
 
+    /// Snapshots the HAR 1.2 log recorded so far, if recording was enabled via `new_with_har`.
+    pub(crate) fn har_log(&self) -> Option<crate::solver::har::HarLog> {
+        self.har.as_ref().map(|h| h.to_har_log())
+    }
+
     fn set_get_headers_order(&mut self) {
-        let order = vec![
+        let order = self.header_profile.get_order();
+        self.client.update().headers_order(order).apply().unwrap();
+    }
+
+    fn set_get_html_headers_order(&mut self) {
+        let order = self.header_profile.get_html_order();
+        self.client.update().headers_order(order).apply().unwrap();
+    }
+
+    fn set_post_headers_order(&mut self) {
+        let order = self.header_profile.post_order();
+        self.client.update().headers_order(order).apply().unwrap();
+    }
+}
+
+/// Which simulated client a `HeaderProfile` impersonates. Determines whether client-hint
+/// headers (`sec-ch-ua*`, `Sec-Fetch-Storage-Access`) are part of the enforced header order at
+/// all - Firefox doesn't implement Client Hints, so a profile claiming to be Firefox that still
+/// sent `sec-ch-ua` would be a more obvious fingerprinting tell than omitting it entirely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderProfileKind {
+    ChromeDesktop,
+    ChromeMobile,
+    Firefox,
+    Custom,
+}
+
+/// Owns the ordered header list `build_client` and the `set_*_headers_order` methods both pull
+/// from, so the TLS/HTTP2 fingerprint (`EmulationProfile`) and the header-order/client-hint
+/// fingerprint always describe the same browser instead of drifting apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderProfile {
+    kind: HeaderProfileKind,
+    emits_client_hints: bool,
+}
+
+impl Default for HeaderProfile {
+    fn default() -> Self {
+        HeaderProfile::chrome_desktop()
+    }
+}
+
+impl HeaderProfile {
+    pub fn chrome_desktop() -> Self {
+        Self {
+            kind: HeaderProfileKind::ChromeDesktop,
+            emits_client_hints: true,
+        }
+    }
+
+    pub fn chrome_mobile() -> Self {
+        Self {
+            kind: HeaderProfileKind::ChromeMobile,
+            emits_client_hints: true,
+        }
+    }
+
+    pub fn firefox() -> Self {
+        Self {
+            kind: HeaderProfileKind::Firefox,
+            emits_client_hints: false,
+        }
+    }
+
+    /// Builds a profile for a target that isn't plain Chrome or Firefox, with an explicit
+    /// client-hint policy.
+    pub fn custom(emits_client_hints: bool) -> Self {
+        Self {
+            kind: HeaderProfileKind::Custom,
+            emits_client_hints,
+        }
+    }
+
+    pub(crate) fn emits_client_hints(&self) -> bool {
+        self.emits_client_hints
+    }
+
+    /// Drops the client-hint headers from `order` when this profile doesn't emit them, keeping
+    /// every other header in its original relative position.
+    fn filter_order(&self, order: Vec<HeaderName>) -> Vec<HeaderName> {
+        if self.emits_client_hints {
+            return order;
+        }
+        const CLIENT_HINT_HEADERS: [&str; 4] = [
+            "sec-ch-ua",
+            "sec-ch-ua-mobile",
+            "sec-ch-ua-platform",
+            "sec-fetch-storage-access",
+        ];
+        order
+            .into_iter()
+            .filter(|name| !CLIENT_HINT_HEADERS.contains(&name.as_str()))
+            .collect()
+    }
+
+    /// Header order for the orchestrate-style `GET` (the `cf-chl`/`cf-chl-ra` carrying calls).
+    fn get_order(&self) -> Vec<HeaderName> {
+        self.filter_order(vec![
             HeaderName::from_static("cache-control"),
             HeaderName::from_static("sec-ch-ua-platform"),
             HeaderName::from_static("user-agent"),
@@ -659,13 +1126,12 @@ pub(crate) async fn initialize_solve(
             HeaderName::from_static("accept-language"),
             HeaderName::from_static("cookie"),
             HeaderName::from_static("priority"),
-        ];
-
-        self.client.update().headers_order(order).apply().unwrap();
+        ])
     }
 
-    fn set_get_html_headers_order(&mut self) {
-        let order = vec![
+    /// Header order for the initial challenge-page `GET`.
+    fn get_html_order(&self) -> Vec<HeaderName> {
+        self.filter_order(vec![
             HeaderName::from_static("sec-ch-ua"),
             HeaderName::from_static("sec-ch-ua-mobile"),
             HeaderName::from_static("sec-ch-ua-platform"),
@@ -681,13 +1147,12 @@ pub(crate) async fn initialize_solve(
             HeaderName::from_static("accept-language"),
             HeaderName::from_static("cookie"),
             HeaderName::from_static("priority"),
-        ];
-
-        self.client.update().headers_order(order).apply().unwrap();
+        ])
     }
 
-    fn set_post_headers_order(&mut self) {
-        let order = vec![
+    /// Header order for the challenge payload `POST`.
+    fn post_order(&self) -> Vec<HeaderName> {
+        self.filter_order(vec![
             HeaderName::from_static("content-length"),
             HeaderName::from_static("sec-ch-ua-platform"),
             HeaderName::from_static("user-agent"),
@@ -707,9 +1172,7 @@ pub(crate) async fn initialize_solve(
             HeaderName::from_static("accept-language"),
             HeaderName::from_static("cookie"),
             HeaderName::from_static("priority"),
-        ];
-
-        self.client.update().headers_order(order).apply().unwrap();
+        ])
     }
 }
 
@@ -749,10 +1212,180 @@ fn generate_widget_id() -> String {
     r
 }
 
+/// A proxy endpoint to route all challenge traffic through. Cloudflare's IP reputation checks
+/// make routing through a residential or datacenter proxy the norm rather than the exception
+/// for this kind of client, so this wraps whichever scheme `rquest::Proxy::all` accepts
+/// (`http://`, `https://`, `socks5://`/`socks5h://`) plus optional basic auth.
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl ProxyConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            username: None,
+            password: None,
+        }
+    }
+
+    pub fn with_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    fn build(&self) -> Result<rquest::Proxy, anyhow::Error> {
+        let mut proxy = rquest::Proxy::all(&self.url)?;
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            proxy = proxy.basic_auth(username, password);
+        }
+        Ok(proxy)
+    }
+}
+
+/// A pooled `Client` plus the cookie jar wired into it at construction, kept together since
+/// `rquest` bakes the cookie provider into the client at build time - there's no way to attach
+/// a different jar to an already-built `Client`.
+struct PooledClient {
+    client: Client,
+    cookie_jar: Arc<Jar>,
+}
+
+/// Identifies clients that can safely share one underlying connection pool: same TLS/HTTP2
+/// fingerprint, same proxy egress, same `User-Agent`. Two `TaskClient`s built with the same key
+/// also end up sharing a cookie jar, which is fine - they're emulating the same browser through
+/// the same egress, so Cloudflare already treats them as one session.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientKey {
+    profile: EmulationProfile,
+    proxy: Option<String>,
+    user_agent: String,
+}
+
+fn client_registry() -> &'static Mutex<FxHashMap<ClientKey, Arc<PooledClient>>> {
+    static REGISTRY: OnceLock<Mutex<FxHashMap<ClientKey, Arc<PooledClient>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(FxHashMap::default()))
+}
+
+/// Looks up a pooled `Client` matching `profile`/`proxy`/`headers.user_agent` before falling
+/// back to `build_client`, so repeated `TaskClient::new*` calls against the same fingerprint
+/// reuse an existing connection pool instead of each paying its own TCP/TLS handshake cost.
+fn pooled_client(
+    profile: EmulationProfile,
+    proxy: Option<ProxyConfig>,
+    headers: Headers,
+    timeout: Duration,
+) -> Result<Arc<PooledClient>, anyhow::Error> {
+    let key = ClientKey {
+        profile,
+        proxy: proxy.as_ref().map(|p| p.url.clone()),
+        user_agent: headers.user_agent.clone(),
+    };
+
+    if let Some(pooled) = client_registry().lock().unwrap().get(&key) {
+        return Ok(pooled.clone());
+    }
+
+    let emulation = EmulationOption::builder()
+        .emulation(profile.emulation())
+        .emulation_os(profile.emulation_os())
+        .build();
+
+    let cookie_jar = Arc::new(Jar::default());
+    let client = build_client(
+        emulation,
+        proxy,
+        headers,
+        cookie_jar.clone(),
+        timeout,
+        profile.header_profile(),
+    )?;
+    let pooled = Arc::new(PooledClient { client, cookie_jar });
+
+    Ok(client_registry()
+        .lock()
+        .unwrap()
+        .entry(key)
+        .or_insert(pooled)
+        .clone())
+}
+
+/// One remembered fetch of the `ov2/av0` response: the body plus the digest it was recorded
+/// under, so the next fetch for the same `(branch, site_key)` can check its own stored entry
+/// for corruption before comparing against it.
+struct ChallengeDigestEntry {
+    digest: String,
+    body: String,
+}
+
+/// Change-detection history of fetched `ov2/av0` responses, indexed by the `(branch, site_key)`
+/// tuple each was fetched for. This is deliberately NOT a fetch-skipping cache: the `ov2/av0`
+/// response carries the per-request `cRay`/`cH` `initialize_solve` needs for *this* solve, so
+/// reusing a stored body would hand back a stale challenge session instead of a fresh one.
+/// What this buys instead is change detection - a silent rewrite of the script under the same
+/// `branch` shows up as a digest mismatch instead of going unnoticed.
+fn challenge_digest_history() -> &'static Mutex<FxHashMap<(String, String), ChallengeDigestEntry>> {
+    static HISTORY: OnceLock<Mutex<FxHashMap<(String, String), ChallengeDigestEntry>>> =
+        OnceLock::new();
+    HISTORY.get_or_init(|| Mutex::new(FxHashMap::default()))
+}
+
+fn sha256_hex(data: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data.as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Records `body`'s digest against the `(branch, site_key)` history, logging a mismatch event
+/// when a previous fetch for the same pair resolved to a different digest (a silent
+/// Cloudflare-side challenge script change) or when a previously recorded entry's stored digest
+/// no longer matches its own stored body (history corruption). Returns the freshly computed
+/// digest so the caller can surface it for version diffing - this never short-circuits the
+/// caller's own fetch, see `challenge_digest_history`.
+fn record_challenge_digest(branch: &str, site_key: &str, body: &str) -> String {
+    let digest = sha256_hex(body);
+    let key = (branch.to_string(), site_key.to_string());
+    let mut history = challenge_digest_history().lock().unwrap();
+
+    if let Some(existing) = history.get(&key) {
+        if sha256_hex(&existing.body) != existing.digest {
+            eprintln!(
+                "⚠️  Challenge digest history corruption detected for {branch}/{site_key}: stored digest no longer matches stored body"
+            );
+        } else if existing.digest != digest {
+            eprintln!(
+                "⚠️  Challenge script digest changed for {branch}/{site_key}: {} -> {digest} (Cloudflare may have shipped a new challenge build)",
+                existing.digest
+            );
+        }
+    }
+
+    history.insert(
+        key,
+        ChallengeDigestEntry {
+            digest: digest.clone(),
+            body: body.to_string(),
+        },
+    );
+
+    digest
+}
+
 fn build_client<P>(
     emulation: P,
-    proxy: Option<String>,
+    proxy: Option<ProxyConfig>,
     headers: Headers,
+    cookie_jar: Arc<Jar>,
+    timeout: Duration,
+    header_profile: HeaderProfile,
 ) -> Result<Client, anyhow::Error>
 where
     P: EmulationProviderFactory,
@@ -768,10 +1401,12 @@ where
         headers.sec_ch_ua_mobile,
         headers.sec_ch_ua_platform,
     ) {
-        header_map.insert("Sec-Fetch-Storage-Access", "active".parse()?);
-        header_map.insert("sec-ch-ua", sec_ch_ua.parse()?);
-        header_map.insert("sec-ch-ua-mobile", sec_ch_ua_mobile.parse()?);
-        header_map.insert("sec-ch-ua-platform", sec_ch_ua_platform.parse()?);
+        if header_profile.emits_client_hints() {
+            header_map.insert("Sec-Fetch-Storage-Access", "active".parse()?);
+            header_map.insert("sec-ch-ua", sec_ch_ua.parse()?);
+            header_map.insert("sec-ch-ua-mobile", sec_ch_ua_mobile.parse()?);
+            header_map.insert("sec-ch-ua-platform", sec_ch_ua_platform.parse()?);
+        }
     }
 
     let mut builder = Client::builder()
@@ -781,23 +1416,22 @@ where
         .brotli(false)
         .deflate(false)
         .zstd(false)
-        .timeout(Duration::from_secs(15))
+        .timeout(timeout)
         .pool_idle_timeout(Some(Duration::from_millis(30000)))
+        .cookie_provider(cookie_jar)
         .default_headers(header_map);
 
     if let Some(p) = proxy {
-        builder = builder.proxy(p);
+        builder = builder.proxy(p.build()?);
     }
 
     builder.build().map_err(|e| anyhow::anyhow!(e))
 }
 
-pub fn decompress_body(
-    bytes: &[u8],
-    encoding: &str,
-) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    match encoding.to_lowercase().as_str() {
-        "gzip" => {
+/// Decodes a single `Content-Encoding` token's worth of compression.
+fn decompress_single(bytes: &[u8], encoding: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    match encoding {
+        "gzip" | "x-gzip" => {
             let mut decoder = flate2::read::GzDecoder::new(bytes);
             let mut decoded = Vec::new();
             decoder.read_to_end(&mut decoded)?;
@@ -821,57 +1455,148 @@ pub fn decompress_body(
             decoder.read_to_end(&mut decoded)?;
             Ok(decoded)
         }
-        "" | "identity" => {
-            Ok(bytes.to_vec())
-        }
+        "" | "identity" => Ok(bytes.to_vec()),
         other => Err(format!("Unsupported encoding: {}", other).into()),
     }
 }
 
-fn debug_html_response(html: &str, cf_ray_header: Option<&str>) {
-    eprintln!("\n=== TURNSTILE DEBUG INFO ===");
-    eprintln!("HTML Length: {} bytes", html.len());
-    
-    if let Some(ray) = cf_ray_header {
-        eprintln!("CF-Ray Header: {}", ray);
+/// Identifies a compression codec from its leading magic bytes, the same kind of signature
+/// sniffing used for media-type detection. Only returns `Some` when the bytes give a confident
+/// answer: gzip (`1F 8B`) and zstd (`28 B5 2F FD`) have fixed magic numbers, zlib/deflate is
+/// detected via the standard CMF/FLG checksum (`(byte0<<8 | byte1) % 31 == 0` with `byte0 ==
+/// 0x78`). Brotli has no magic number at all, so it's only inferred when the declared codec
+/// already claims `br` and the body isn't empty - otherwise `None` means "trust `declared`".
+fn sniff_codec(bytes: &[u8], declared: &str) -> Option<&'static str> {
+    if bytes.len() >= 2 && bytes[0] == 0x1F && bytes[1] == 0x8B {
+        return Some("gzip");
     }
-    
-    if html.contains("_cf_chl_opt") {
-        eprintln!("✅ Found _cf_chl_opt marker");
-    } else {
-        eprintln!("❌ No _cf_chl_opt marker found");
+    if bytes.len() >= 4 && bytes[0..4] == [0x28, 0xB5, 0x2F, 0xFD] {
+        return Some("zstd");
     }
-    
-    let script_count = html.matches("<script").count();
-    eprintln!("Script tags: {}", script_count);
-    
-    let cray_regex = Regex::new(r#"(?:cRay|c_ray)["']?\s*[:=]\s*["']?([a-f0-9]{16})"#).unwrap();
-    if let Some(cap) = cray_regex.captures(html) {
-        if let Some(m) = cap.get(1) {
-            eprintln!("✅ Found cRay in HTML: {}", m.as_str());
-        }
-    } else {
-        eprintln!("❌ No cRay found in HTML");
+    if bytes.len() >= 2 && bytes[0] == 0x78 && (((bytes[0] as u16) << 8 | bytes[1] as u16) % 31 == 0) {
+        return Some("deflate");
     }
-    
-    let ch_regex = Regex::new(r#"(?:cH|ch)["']?\s*[:=]\s*["']?([a-zA-Z0-9_-]{20,})"#).unwrap();
-    if let Some(cap) = ch_regex.captures(html) {
-        if let Some(m) = cap.get(1) {
-            eprintln!("✅ Found ch in HTML: {}", m.as_str());
-        }
-    } else {
-        eprintln!("❌ No ch found in HTML");
+    if !bytes.is_empty() && matches!(declared, "br" | "brotli") {
+        return Some("br");
     }
-    
-    if html.contains("<iframe") {
-        eprintln!("⚠️  Response contains iframe");
+    None
+}
+
+/// Decodes a (possibly empty, possibly comma-separated/stacked) `Content-Encoding` header
+/// value. Per RFC 9110 a stacked value like `gzip, br` means the body was gzip-encoded first
+/// and then brotli-encoded on top, so decoding has to undo that in reverse - brotli first,
+/// then gzip - the same order servo's `http_loader` walks its decoder chain in. Cloudflare
+/// responses are sometimes mislabeled or doubly-encoded, so each layer is re-checked against
+/// its magic bytes via `sniff_codec` before decoding, preferring the sniffed codec whenever it
+/// disagrees with the declared one.
+pub fn decompress_body(
+    bytes: &[u8],
+    encoding: &str,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let lower = encoding.to_lowercase();
+    let codecs: Vec<&str> = lower.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
+
+    if codecs.is_empty() {
+        return Ok(bytes.to_vec());
     }
-    if html.contains("window.location") || html.contains("document.location") {
-        eprintln!("⚠️  Response contains redirect JavaScript");
+
+    let mut data = bytes.to_vec();
+    for declared in codecs.iter().rev() {
+        // An inconclusive sniff falls through to identity, not `declared` - the whole point of
+        // sniffing is that the declared `Content-Encoding` can't be trusted, so re-trusting it
+        // here on a `None` would defeat the purpose.
+        let effective = sniff_codec(&data, declared).unwrap_or("identity");
+        data = decompress_single(&data, effective).map_err(|e| {
+            format!(
+                "failed to decode '{effective}' layer (declared '{declared}'): {e}"
+            )
+        })?;
     }
-    if html.contains("Checking your Browser") {
-        eprintln!("⚠️  Response is loading/challenge page");
+    Ok(data)
+}
+
+/// What kind of page a fetched challenge response turned out to be, inferred from the same
+/// markers `debug_html_response` used to eyeball by hand (iframe, client-side redirect script,
+/// the interstitial's loading copy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum PageKind {
+    Challenge,
+    Interstitial,
+    Redirect,
+    Unknown,
+}
+
+/// Machine-readable summary of a fetched challenge page, replacing the old `eprintln!`-only
+/// `debug_html_response`. `Serialize` so a caller can ship it to a logging pipeline as JSON;
+/// `Display` renders the same human-readable report the emoji-annotated stderr dump used to,
+/// for whoever's eyeballing a single run in a terminal.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ChallengeReport {
+    pub html_len: usize,
+    pub cf_ray: Option<String>,
+    pub has_cf_chl_opt: bool,
+    pub script_count: usize,
+    pub extracted_cray: Option<String>,
+    pub extracted_ch: Option<String>,
+    pub page_kind: PageKind,
+}
+
+impl std::fmt::Display for ChallengeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "=== TURNSTILE DEBUG INFO ===")?;
+        writeln!(f, "HTML Length: {} bytes", self.html_len)?;
+        if let Some(ray) = &self.cf_ray {
+            writeln!(f, "CF-Ray Header: {ray}")?;
+        }
+        writeln!(
+            f,
+            "{} _cf_chl_opt marker",
+            if self.has_cf_chl_opt { "✅ Found" } else { "❌ No" }
+        )?;
+        writeln!(f, "Script tags: {}", self.script_count)?;
+        match &self.extracted_cray {
+            Some(cray) => writeln!(f, "✅ Found cRay in HTML: {cray}")?,
+            None => writeln!(f, "❌ No cRay found in HTML")?,
+        }
+        match &self.extracted_ch {
+            Some(ch) => writeln!(f, "✅ Found ch in HTML: {ch}")?,
+            None => writeln!(f, "❌ No ch found in HTML")?,
+        }
+        writeln!(f, "Page kind: {:?}", self.page_kind)?;
+        write!(f, "=== END DEBUG INFO ===")
+    }
+}
+
+fn build_challenge_report(html: &str, cf_ray_header: Option<&str>) -> ChallengeReport {
+    let cray_regex = Regex::new(r#"(?:cRay|c_ray)["']?\s*[:=]\s*["']?([a-f0-9]{16})"#).unwrap();
+    let extracted_cray = cray_regex
+        .captures(html)
+        .and_then(|cap| cap.get(1))
+        .map(|m| m.as_str().to_string());
+
+    let ch_regex = Regex::new(r#"(?:cH|ch)["']?\s*[:=]\s*["']?([a-zA-Z0-9_-]{20,})"#).unwrap();
+    let extracted_ch = ch_regex
+        .captures(html)
+        .and_then(|cap| cap.get(1))
+        .map(|m| m.as_str().to_string());
+
+    let page_kind = if html.contains("window.location") || html.contains("document.location") {
+        PageKind::Redirect
+    } else if html.contains("Checking your Browser") {
+        PageKind::Interstitial
+    } else if html.contains("<iframe") {
+        PageKind::Challenge
+    } else {
+        PageKind::Unknown
+    };
+
+    ChallengeReport {
+        html_len: html.len(),
+        cf_ray: cf_ray_header.map(|s| s.to_string()),
+        has_cf_chl_opt: html.contains("_cf_chl_opt"),
+        script_count: html.matches("<script").count(),
+        extracted_cray,
+        extracted_ch,
+        page_kind,
     }
-    
-    eprintln!("=== END DEBUG INFO ===\n");
 }
\ No newline at end of file
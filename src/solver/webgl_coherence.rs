@@ -0,0 +1,103 @@
+use crate::solver::user_fingerprint::Fingerprint;
+use serde_json::{json, Map, Value};
+
+/// One internally-consistent (vendor, masked renderer, unmasked renderer, WebGPU adapter info)
+/// tuple, modeled after a real GPU/driver/ANGLE combination a browser would actually report.
+/// `Fingerprint::webgl`'s fields are otherwise assembled independently per-sample, so nothing
+/// stops a random pick from pairing an unmasked NVIDIA renderer with an ANGLE-Intel masked
+/// string - this table is what lets `validate_or_repair` catch and fix that.
+///
+/// `extensions` documents the supported-extension list a real driver for this adapter would
+/// report; `Fingerprint` doesn't expose a field for it yet, so it isn't written anywhere below,
+/// but it's kept here so a future extensions field has a ready-made, consistent source.
+struct KnownAdapter {
+    masked_vendor: &'static str,
+    masked_renderer: &'static str,
+    unmasked_vendor: &'static str,
+    unmasked_renderer: &'static str,
+    #[allow(dead_code)]
+    extensions: &'static [&'static str],
+    webgpu_vendor: &'static str,
+    webgpu_architecture: &'static str,
+    webgpu_description: &'static str,
+}
+
+const KNOWN_ADAPTERS: &[KnownAdapter] = &[
+    KnownAdapter {
+        masked_vendor: "Google Inc. (NVIDIA)",
+        masked_renderer: "ANGLE (NVIDIA, NVIDIA GeForce RTX 3060 Direct3D11 vs_5_0 ps_5_0, D3D11)",
+        unmasked_vendor: "NVIDIA Corporation",
+        unmasked_renderer: "NVIDIA GeForce RTX 3060/PCIe/SSE2",
+        extensions: &["EXT_color_buffer_float", "OES_texture_float_linear"],
+        webgpu_vendor: "nvidia",
+        webgpu_architecture: "ampere",
+        webgpu_description: "NVIDIA GeForce RTX 3060",
+    },
+    KnownAdapter {
+        masked_vendor: "Google Inc. (Intel)",
+        masked_renderer: "ANGLE (Intel, Intel(R) UHD Graphics 630 Direct3D11 vs_5_0 ps_5_0, D3D11)",
+        unmasked_vendor: "Intel Inc.",
+        unmasked_renderer: "Intel(R) UHD Graphics 630",
+        extensions: &["EXT_color_buffer_float"],
+        webgpu_vendor: "intel",
+        webgpu_architecture: "gen9",
+        webgpu_description: "Intel(R) UHD Graphics 630",
+    },
+    KnownAdapter {
+        masked_vendor: "Google Inc. (AMD)",
+        masked_renderer: "ANGLE (AMD, AMD Radeon RX 6600 XT Direct3D11 vs_5_0 ps_5_0, D3D11)",
+        unmasked_vendor: "ATI Technologies Inc.",
+        unmasked_renderer: "AMD Radeon RX 6600 XT",
+        extensions: &["EXT_color_buffer_float", "WEBGL_debug_renderer_info"],
+        webgpu_vendor: "amd",
+        webgpu_architecture: "rdna-2",
+        webgpu_description: "AMD Radeon RX 6600 XT",
+    },
+];
+
+impl KnownAdapter {
+    fn navigator_gpu_data(&self) -> Map<String, Value> {
+        match json!({
+            "vendor": self.webgpu_vendor,
+            "architecture": self.webgpu_architecture,
+            "description": self.webgpu_description,
+        }) {
+            Value::Object(map) => map,
+            _ => unreachable!("object literal always serializes to Value::Object"),
+        }
+    }
+}
+
+/// Checks whether `fingerprint.webgl`'s masked/unmasked vendor and renderer strings describe
+/// the same adapter in `KNOWN_ADAPTERS`, repairing both the masked/unmasked strings and
+/// `navigator_gpu_data` in place if not. Returns `true` when a repair was made, so
+/// `TurnstileSolver::new` can log how often generated fingerprints needed fixing up.
+pub fn validate_or_repair(fingerprint: &mut Fingerprint) -> bool {
+    let webgl = &fingerprint.webgl;
+    let already_consistent = KNOWN_ADAPTERS.iter().any(|adapter| {
+        adapter.masked_vendor == webgl.masked_vendor
+            && adapter.masked_renderer == webgl.masked_renderer
+            && adapter.unmasked_vendor == webgl.unmasked_vendor
+            && adapter.unmasked_renderer == webgl.unmasked_renderer
+    });
+    if already_consistent {
+        return false;
+    }
+
+    // Repair to the adapter whose unmasked vendor best matches what was generated, falling
+    // back to the first known-good adapter - an incoherent fingerprint is worse than a
+    // generic-but-consistent one.
+    let repaired = KNOWN_ADAPTERS
+        .iter()
+        .find(|adapter| adapter.unmasked_vendor == webgl.unmasked_vendor)
+        .or_else(|| KNOWN_ADAPTERS.first())
+        .expect("KNOWN_ADAPTERS is never empty");
+
+    fingerprint.webgl.masked_vendor = repaired.masked_vendor.to_string();
+    fingerprint.webgl.masked_renderer = repaired.masked_renderer.to_string();
+    fingerprint.webgl.unmasked_vendor = repaired.unmasked_vendor.to_string();
+    fingerprint.webgl.unmasked_renderer = repaired.unmasked_renderer.to_string();
+    fingerprint.webgl.navigator_gpu_data = Some(repaired.navigator_gpu_data());
+
+    true
+}
@@ -0,0 +1,125 @@
+use oxc_allocator::Allocator;
+use oxc_ast_visit::Visit;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+use sha2::{Digest, Sha256};
+use cf::parser::vm::ScriptVisitor;
+use std::fs;
+use std::path::Path;
+
+/// Expectations parsed out of a corpus fixture's leading `// expect-...`/`// ignore: ...`
+/// comment block - the same header-directive idea test harnesses like compiletest use, adapted
+/// to `ScriptVisitor`'s recovered fields instead of compiler diagnostics.
+#[derive(Debug, Default)]
+struct Directives {
+    expect_charset: Option<String>,
+    expect_init_arg: Option<String>,
+    expect_main_len_min: Option<usize>,
+    expect_initial_sha256: Option<String>,
+    ignore: Option<String>,
+}
+
+fn parse_directives(source: &str) -> Directives {
+    let mut directives = Directives::default();
+    for line in source.lines() {
+        let trimmed = line.trim();
+        let Some(body) = trimmed.strip_prefix("//") else {
+            break;
+        };
+        let body = body.trim();
+
+        if let Some(value) = body.strip_prefix("expect-charset:") {
+            directives.expect_charset = Some(value.trim().to_string());
+        } else if let Some(value) = body.strip_prefix("expect-init-arg:") {
+            directives.expect_init_arg = Some(value.trim().to_string());
+        } else if let Some(value) = body.strip_prefix("expect-main-len-min:") {
+            directives.expect_main_len_min = value.trim().parse().ok();
+        } else if let Some(value) = body.strip_prefix("expect-initial-sha256:") {
+            directives.expect_initial_sha256 = Some(value.trim().to_string());
+        } else if let Some(value) = body.strip_prefix("ignore:") {
+            directives.ignore = Some(value.trim().to_string());
+        }
+    }
+    directives
+}
+
+/// Runs `ScriptVisitor` over `source` and diffs the result against `directives`, returning
+/// `Err(reason)` for the first mismatch found.
+fn check_fixture(source: &str, directives: &Directives) -> Result<(), String> {
+    let allocator = Allocator::default();
+    let ret = Parser::new(&allocator, source, SourceType::default()).parse();
+
+    let mut visitor = ScriptVisitor::default();
+    visitor.visit_program(&ret.program);
+
+    if let Some(expected) = &directives.expect_charset {
+        let actual = visitor.compressor_charset.as_deref().unwrap_or("");
+        if actual != expected {
+            return Err(format!("expected charset {expected:?}, got {actual:?}"));
+        }
+    }
+
+    if let Some(expected) = &directives.expect_init_arg {
+        let actual = visitor.init_argument.as_deref().unwrap_or("");
+        if actual != expected {
+            return Err(format!("expected init_argument {expected:?}, got {actual:?}"));
+        }
+    }
+
+    if let Some(min_len) = directives.expect_main_len_min {
+        let actual_len = visitor.main_vm().map_or(0, |c| c.value.len());
+        if actual_len < min_len {
+            return Err(format!(
+                "expected main_vm length >= {min_len}, got {actual_len}"
+            ));
+        }
+    }
+
+    if let Some(expected_hash) = &directives.expect_initial_sha256 {
+        let actual = visitor.initial_vm().map_or("", |c| c.value.as_str());
+        let digest = format!("{:x}", Sha256::digest(actual.as_bytes()));
+        if &digest != expected_hash {
+            return Err(format!(
+                "expected initial_vm sha256 {expected_hash}, got {digest}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Regression suite over real-world (or representative) sample scripts under `tests/corpus/`:
+/// each fixture's leading directive comments say what `ScriptVisitor` should recover from it,
+/// and a fixture carrying `// ignore: <reason>` is skipped rather than failing the whole run -
+/// for samples from an obfuscator variant this crate doesn't support yet, that's expected, not a
+/// bug, so it shouldn't block every other fixture from being checked.
+#[test]
+fn corpus_matches_directives() {
+    let corpus_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut failures = Vec::new();
+
+    for entry in fs::read_dir(&corpus_dir).expect("tests/corpus directory should exist") {
+        let path = entry.expect("readable corpus directory entry").path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("js") {
+            continue;
+        }
+
+        let source = fs::read_to_string(&path).expect("readable corpus fixture");
+        let directives = parse_directives(&source);
+
+        if let Some(reason) = &directives.ignore {
+            eprintln!("skipping {}: {reason}", path.display());
+            continue;
+        }
+
+        if let Err(reason) = check_fixture(&source, &directives) {
+            failures.push(format!("{}: {reason}", path.display()));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "corpus fixtures did not match their directives:\n{}",
+        failures.join("\n")
+    );
+}